@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+
+use bevy::{input::InputSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+/// Everything the player can do in game, whatever the input device.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameAction {
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Fire,
+    SwitchWeapon,
+    Pause,
+}
+
+impl GameAction {
+    pub const ALL: [GameAction; 6] = [
+        GameAction::MoveLeft,
+        GameAction::MoveRight,
+        GameAction::Jump,
+        GameAction::Fire,
+        GameAction::SwitchWeapon,
+        GameAction::Pause,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GameAction::MoveLeft => "Move Left",
+            GameAction::MoveRight => "Move Right",
+            GameAction::Jump => "Jump",
+            GameAction::Fire => "Fire",
+            GameAction::SwitchWeapon => "Switch Weapon",
+            GameAction::Pause => "Pause",
+        }
+    }
+}
+
+/// Menu navigation. Its bindings are fixed so that menus stay usable whatever gets rebound.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MenuAction {
+    Up,
+    Down,
+    Confirm,
+    Back,
+}
+
+impl MenuAction {
+    pub const ALL: [MenuAction; 4] = [
+        MenuAction::Up,
+        MenuAction::Down,
+        MenuAction::Confirm,
+        MenuAction::Back,
+    ];
+}
+
+const MENU_KEYS: [(MenuAction, KeyCode); 6] = [
+    (MenuAction::Up, KeyCode::Up),
+    (MenuAction::Down, KeyCode::Down),
+    (MenuAction::Confirm, KeyCode::Return),
+    (MenuAction::Confirm, KeyCode::Space),
+    (MenuAction::Back, KeyCode::Escape),
+    (MenuAction::Back, KeyCode::Back),
+];
+
+const MENU_GAMEPAD: [(MenuAction, GamepadInput); 6] = [
+    (MenuAction::Up, GamepadInput::Button(GamepadButtonType::DPadUp)),
+    (MenuAction::Up, GamepadInput::AxisPositive(GamepadAxisType::LeftStickY)),
+    (MenuAction::Down, GamepadInput::Button(GamepadButtonType::DPadDown)),
+    (MenuAction::Down, GamepadInput::AxisNegative(GamepadAxisType::LeftStickY)),
+    (MenuAction::Confirm, GamepadInput::Button(GamepadButtonType::South)),
+    (MenuAction::Back, GamepadInput::Button(GamepadButtonType::East)),
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GamepadInput {
+    Button(GamepadButtonType),
+    AxisPositive(GamepadAxisType),
+    AxisNegative(GamepadAxisType),
+}
+
+/// How far a stick must be pushed to trigger an action.
+const AXIS_THRESHOLD: f32 = 0.5;
+
+/// Axes that can be bound to actions.
+const BINDABLE_AXES: [GamepadAxisType; 6] = [
+    GamepadAxisType::LeftStickX,
+    GamepadAxisType::LeftStickY,
+    GamepadAxisType::RightStickX,
+    GamepadAxisType::RightStickY,
+    GamepadAxisType::DPadX,
+    GamepadAxisType::DPadY,
+];
+
+impl GamepadInput {
+    pub fn label(&self) -> String {
+        match self {
+            GamepadInput::Button(button) => format!("{:?}", button),
+            GamepadInput::AxisPositive(axis) => format!("{:?} +", axis),
+            GamepadInput::AxisNegative(axis) => format!("{:?} -", axis),
+        }
+    }
+
+    /// Returns the input being used on any gamepad, preferring newly pressed buttons.
+    pub fn detect(
+        gamepads: &Gamepads,
+        buttons: &Input<GamepadButton>,
+        axes: &Axis<GamepadAxis>,
+    ) -> Option<GamepadInput> {
+        if let Some(button) = buttons.get_just_pressed().next() {
+            return Some(GamepadInput::Button(button.1));
+        }
+        for gamepad in gamepads.iter() {
+            for axis in BINDABLE_AXES {
+                match axes.get(GamepadAxis(*gamepad, axis)) {
+                    Some(value) if value > AXIS_THRESHOLD => {
+                        return Some(GamepadInput::AxisPositive(axis))
+                    }
+                    Some(value) if value < -AXIS_THRESHOLD => {
+                        return Some(GamepadInput::AxisNegative(axis))
+                    }
+                    _ => (),
+                }
+            }
+        }
+        None
+    }
+
+    fn is_active(
+        &self,
+        gamepad: Gamepad,
+        buttons: &Input<GamepadButton>,
+        axes: &Axis<GamepadAxis>,
+    ) -> bool {
+        match *self {
+            GamepadInput::Button(button) => buttons.pressed(GamepadButton(gamepad, button)),
+            GamepadInput::AxisPositive(axis) => axes
+                .get(GamepadAxis(gamepad, axis))
+                .map_or(false, |value| value > AXIS_THRESHOLD),
+            GamepadInput::AxisNegative(axis) => axes
+                .get(GamepadAxis(gamepad, axis))
+                .map_or(false, |value| value < -AXIS_THRESHOLD),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputBindings {
+    pub keys: HashMap<GameAction, KeyCode>,
+    pub gamepad: HashMap<GameAction, GamepadInput>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        let keys = HashMap::from([
+            (GameAction::MoveLeft, KeyCode::Left),
+            (GameAction::MoveRight, KeyCode::Right),
+            (GameAction::Jump, KeyCode::Up),
+            (GameAction::Fire, KeyCode::Space),
+            (GameAction::SwitchWeapon, KeyCode::Q),
+            (GameAction::Pause, KeyCode::Escape),
+        ]);
+        let gamepad = HashMap::from([
+            (
+                GameAction::MoveLeft,
+                GamepadInput::AxisNegative(GamepadAxisType::LeftStickX),
+            ),
+            (
+                GameAction::MoveRight,
+                GamepadInput::AxisPositive(GamepadAxisType::LeftStickX),
+            ),
+            (GameAction::Jump, GamepadInput::Button(GamepadButtonType::South)),
+            (GameAction::Fire, GamepadInput::Button(GamepadButtonType::West)),
+            (
+                GameAction::SwitchWeapon,
+                GamepadInput::Button(GamepadButtonType::North),
+            ),
+            (GameAction::Pause, GamepadInput::Button(GamepadButtonType::Start)),
+        ]);
+        InputBindings { keys, gamepad }
+    }
+}
+
+const BINDINGS_KEY: &str = "bindings";
+
+impl InputBindings {
+    /// Loads the saved bindings, falling back to the defaults.
+    pub fn load() -> Self {
+        let mut bindings: InputBindings =
+            storage::load_ron(BINDINGS_KEY, "input bindings").unwrap_or_default();
+        // Actions added since the bindings were saved get their default input, if still free.
+        let defaults = InputBindings::default();
+        add_missing(&mut bindings.keys, defaults.keys);
+        add_missing(&mut bindings.gamepad, defaults.gamepad);
+        bindings
+    }
+
+    /// Binds `key` to `action`. An action already using `key` gets the previous key of `action`
+    /// and is returned, so that one key never triggers two actions.
+    pub fn bind_key(&mut self, action: GameAction, key: KeyCode) -> Option<GameAction> {
+        bind(&mut self.keys, action, key)
+    }
+
+    /// Same as `bind_key` for gamepads.
+    pub fn bind_gamepad(&mut self, action: GameAction, input: GamepadInput) -> Option<GameAction> {
+        bind(&mut self.gamepad, action, input)
+    }
+
+    pub fn save(&self) {
+        storage::save_ron(BINDINGS_KEY, "input bindings", self);
+    }
+}
+
+fn add_missing<T: PartialEq>(
+    bindings: &mut HashMap<GameAction, T>,
+    defaults: HashMap<GameAction, T>,
+) {
+    for (action, input) in defaults {
+        if !bindings.contains_key(&action) && !bindings.values().any(|bound| *bound == input) {
+            bindings.insert(action, input);
+        }
+    }
+}
+
+fn bind<T: Copy + PartialEq>(
+    bindings: &mut HashMap<GameAction, T>,
+    action: GameAction,
+    input: T,
+) -> Option<GameAction> {
+    let conflict = bindings
+        .iter()
+        .find(|(other, bound)| **other != action && **bound == input)
+        .map(|(other, _)| *other);
+    let previous = bindings.insert(action, input);
+    if let Some(other) = conflict {
+        match previous {
+            Some(previous) => bindings.insert(other, previous),
+            None => bindings.remove(&other),
+        };
+    }
+    conflict
+}
+
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InputBindings::load())
+            .init_resource::<Input<GameAction>>()
+            .init_resource::<Input<MenuAction>>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                update_actions.system().after(InputSystem),
+            )
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                update_menu_actions.system().after(InputSystem),
+            );
+    }
+}
+
+fn update_actions(
+    bindings: Res<InputBindings>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    mut actions: ResMut<Input<GameAction>>,
+) {
+    actions.clear();
+    for action in GameAction::ALL {
+        let key_pressed = bindings
+            .keys
+            .get(&action)
+            .map_or(false, |key| keyboard_input.pressed(*key));
+        let gamepad_pressed = bindings.gamepad.get(&action).map_or(false, |input| {
+            gamepads
+                .iter()
+                .any(|gamepad| input.is_active(*gamepad, &gamepad_buttons, &gamepad_axes))
+        });
+
+        if key_pressed || gamepad_pressed {
+            actions.press(action);
+        } else if actions.pressed(action) {
+            actions.release(action);
+        }
+    }
+}
+
+fn update_menu_actions(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    mut menu_actions: ResMut<Input<MenuAction>>,
+) {
+    menu_actions.clear();
+    for action in MenuAction::ALL {
+        let key_pressed = MENU_KEYS
+            .iter()
+            .any(|(bound, key)| *bound == action && keyboard_input.pressed(*key));
+        let gamepad_pressed = MENU_GAMEPAD.iter().any(|(bound, input)| {
+            *bound == action
+                && gamepads
+                    .iter()
+                    .any(|gamepad| input.is_active(*gamepad, &gamepad_buttons, &gamepad_axes))
+        });
+
+        if key_pressed || gamepad_pressed {
+            menu_actions.press(action);
+        } else if menu_actions.pressed(action) {
+            menu_actions.release(action);
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::super::AppState;
+use super::{
+    BulletFiredEvent, Enemy, Faction, GameDirection, Health, Jumper, Level, LivingBeing,
+    LivingBeingDeathEvent, Materials, Player, Weapon, WeaponKind, WinningZone, MONSTER_GROUP,
+};
+
+pub const BOSS_SIZE: f32 = 2.4;
+const BOSS_HEALTH: u32 = 30;
+const BOSS_JUMP_IMPULSE: f32 = 16.;
+/// How far into the arena the player walks before the exit behind them closes.
+const ARENA_TRIGGER_DEPTH: f32 = 2.;
+const GATE_HEIGHT: f32 = 12.;
+
+/// The end of a level where a boss guards the winning zone.
+pub struct BossArena {
+    /// First column of the arena.
+    pub from: f32,
+    /// Column of the winning zone.
+    pub to: f32,
+    /// Height of the arena's floor surface.
+    pub ground: f32,
+    pub fight: BossFight,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BossFight {
+    Waiting,
+    Started,
+    Won,
+}
+
+#[derive(Component)]
+pub struct Boss {
+    /// From 1 to 3, the boss getting more aggressive as it loses health.
+    pub phase: usize,
+    pub facing_direction: GameDirection,
+    /// Seconds before the next jump.
+    pub jump_timer: f32,
+}
+
+/// Wall closing the arena behind the player during the fight.
+#[derive(Component)]
+pub struct ArenaGate;
+
+/// Keeps a winning zone from ending the level.
+#[derive(Component)]
+pub struct Locked;
+
+/// Sent by the system despawning the boss, as it can no longer be queried afterwards.
+pub struct BossDefeatedEvent;
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BossDefeatedEvent>().add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(start_boss_fight.system())
+                .with_system(boss_behaviour.system())
+                .with_system(on_boss_fight_death.system()),
+        );
+    }
+}
+
+pub fn insert_boss_arena(commands: &mut Commands, level: &Level, from: usize) {
+    let floor = level.tiles.standable_rows(from).last().copied().unwrap_or(1);
+    commands.insert_resource(BossArena {
+        from: from as f32,
+        to: level.winning_zone,
+        ground: floor as f32 + 0.5,
+        fight: BossFight::Waiting,
+    });
+}
+
+/// Attacks for each phase, from aimed spit to a hail of bullets.
+fn boss_weapon(phase: usize) -> Weapon {
+    let mut weapon = match phase {
+        1 => {
+            let mut weapon = Weapon::new(WeaponKind::Spit);
+            weapon.projectile_count = 3;
+            weapon.spread = 0.5;
+            weapon
+        }
+        2 => Weapon::new(WeaponKind::Shotgun),
+        _ => {
+            let mut weapon = Weapon::new(WeaponKind::RapidFire);
+            weapon.fire_rate = 4.;
+            weapon.spread = 0.3;
+            weapon
+        }
+    };
+    weapon.reserve = None;
+    weapon
+}
+
+fn phase_of(health: &Health) -> usize {
+    match health.current * 3 {
+        current if current > health.max * 2 => 1,
+        current if current > health.max => 2,
+        _ => 3,
+    }
+}
+
+fn insert_boss_at(commands: &mut Commands, materials: &Res<Materials>, x: f32, y: f32) {
+    let rigid_body = RigidBodyBundle {
+        position: Vec2::new(x, y).into(),
+        mass_properties: RigidBodyMassPropsFlags::ROTATION_LOCKED.into(),
+        activation: RigidBodyActivation::cannot_sleep().into(),
+        forces: RigidBodyForces {
+            gravity_scale: 3.,
+            ..Default::default()
+        }.into(),
+        ..Default::default()
+    };
+
+    let half_size = BOSS_SIZE / 2.;
+    let collider = ColliderBundle {
+        shape: ColliderShape::round_cuboid(half_size - 0.1, half_size - 0.1, 0.1).into(),
+        flags: ColliderFlags {
+            active_events: ActiveEvents::CONTACT_EVENTS,
+            collision_groups: InteractionGroups::new(MONSTER_GROUP, u32::MAX),
+            ..Default::default()
+        }.into(),
+        ..Default::default()
+    };
+
+    let sprite = SpriteBundle {
+        sprite: Sprite {
+            color: materials.boss_material.clone(),
+            custom_size: Vec2::new(BOSS_SIZE, BOSS_SIZE).into(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    commands
+        .spawn_bundle(sprite)
+        .insert_bundle(rigid_body)
+        .insert_bundle(collider)
+        .insert(RigidBodyPositionSync::Discrete)
+        .insert(LivingBeing)
+        .insert(Health::new(BOSS_HEALTH, 0.3))
+        .insert(Enemy)
+        .insert(Boss {
+            phase: 1,
+            facing_direction: GameDirection::Left,
+            jump_timer: 0.,
+        })
+        .insert(Jumper::new(BOSS_JUMP_IMPULSE, 0.05, 0.3, 1.))
+        .insert(boss_weapon(1));
+}
+
+fn insert_gate_at(commands: &mut Commands, materials: &Res<Materials>, x: f32, ground: f32) {
+    let rigid_body = RigidBodyBundle {
+        position: Vec2::new(x, ground + GATE_HEIGHT / 2.).into(),
+        body_type: RigidBodyType::Static.into(),
+        ..Default::default()
+    };
+
+    let collider = ColliderBundle {
+        shape: ColliderShape::cuboid(0.5, GATE_HEIGHT / 2.).into(),
+        ..Default::default()
+    };
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: materials.gate_material.clone(),
+                custom_size: Vec2::new(1., GATE_HEIGHT).into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert_bundle(rigid_body)
+        .insert_bundle(collider)
+        .insert(RigidBodyPositionSync::Discrete)
+        .insert(ArenaGate);
+}
+
+/// Closes the arena behind the player walking into it and brings in the boss.
+fn start_boss_fight(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    arena: Option<ResMut<BossArena>>,
+    players: Query<&RigidBodyPositionComponent, With<Player>>,
+) {
+    let mut arena = match arena {
+        Some(arena) if arena.fight == BossFight::Waiting => arena,
+        _ => return,
+    };
+    let entered = players
+        .iter()
+        .any(|player| player.position.translation.x > arena.from + ARENA_TRIGGER_DEPTH);
+    if !entered {
+        return;
+    }
+    insert_gate_at(&mut commands, &materials, arena.from - 1., arena.ground);
+    insert_boss_at(
+        &mut commands,
+        &materials,
+        arena.to - 4.,
+        arena.ground + BOSS_SIZE / 2.,
+    );
+    arena.fight = BossFight::Started;
+}
+
+/// Paces the arena and fires aimed spit at first, then chases the player with jumps and
+/// shotgun blasts, and charges spraying bullets once badly hurt.
+fn boss_behaviour(
+    time: Res<Time>,
+    materials: Res<Materials>,
+    arena: Option<Res<BossArena>>,
+    mut send_fire_event: EventWriter<BulletFiredEvent>,
+    players: Query<&RigidBodyPositionComponent, With<Player>>,
+    mut bosses: Query<
+        (
+            &mut Boss,
+            &Health,
+            &RigidBodyPositionComponent,
+            &mut RigidBodyVelocityComponent,
+            &mut Jumper,
+            &mut Weapon,
+            &mut Sprite,
+        ),
+        Without<Player>,
+    >,
+) {
+    let (arena, player) = match (arena, players.get_single()) {
+        (Some(arena), Ok(player)) => (arena, player.position.translation),
+        _ => return,
+    };
+    let player = Vec2::new(player.x, player.y);
+    for (mut boss, health, position, mut velocity, mut jumper, mut weapon, mut sprite) in
+        bosses.iter_mut()
+    {
+        let translation = position.position.translation;
+        let center = Vec2::new(translation.x, translation.y);
+
+        let phase = phase_of(health);
+        if phase != boss.phase {
+            boss.phase = phase;
+            *weapon = boss_weapon(phase);
+            if phase == 3 {
+                sprite.color = materials.boss_enraged_material.clone();
+            }
+        }
+
+        let (speed, jump_interval) = match boss.phase {
+            1 => (2., None),
+            2 => (3., Some(3.)),
+            _ => (5., Some(1.5)),
+        };
+        if boss.phase == 1 {
+            if center.x < arena.from + BOSS_SIZE {
+                boss.facing_direction = GameDirection::Right;
+            } else if center.x > arena.to - BOSS_SIZE {
+                boss.facing_direction = GameDirection::Left;
+            }
+        } else {
+            boss.facing_direction = if player.x < center.x {
+                GameDirection::Left
+            } else {
+                GameDirection::Right
+            };
+        }
+        let direction = match boss.facing_direction {
+            GameDirection::Left => -1.,
+            GameDirection::Right => 1.,
+        };
+        velocity.linvel = Vec2::new(direction * speed, velocity.linvel.y).into();
+
+        if let Some(interval) = jump_interval {
+            boss.jump_timer -= time.delta_seconds();
+            if boss.jump_timer <= 0. {
+                boss.jump_timer = interval;
+                jumper.request_jump();
+            }
+        }
+
+        for direction in weapon.fire((player - center).normalize_or_zero()) {
+            send_fire_event.send(BulletFiredEvent {
+                position: center,
+                direction,
+                speed: weapon.projectile_speed,
+                damage: weapon.damage,
+                bounces: weapon.bounces,
+                faction: Faction::Monsters,
+            });
+        }
+    }
+}
+
+/// Opens the arena once the boss is dead, and sets the fight up again when the player dies.
+fn on_boss_fight_death(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    arena: Option<ResMut<BossArena>>,
+    mut boss_defeated_events: EventReader<BossDefeatedEvent>,
+    mut living_being_death_events: EventReader<LivingBeingDeathEvent>,
+    bosses: Query<Entity, With<Boss>>,
+    players: Query<(), With<Player>>,
+    gates: Query<Entity, With<ArenaGate>>,
+    mut winning_zones: Query<(Entity, &mut Sprite), (With<WinningZone>, With<Locked>)>,
+) {
+    let mut arena = match arena {
+        Some(arena) if arena.fight == BossFight::Started => arena,
+        _ => return,
+    };
+    if boss_defeated_events.iter().next().is_some() {
+        for gate in gates.iter() {
+            commands.entity(gate).despawn_recursive();
+        }
+        for (winning_zone, mut sprite) in winning_zones.iter_mut() {
+            sprite.color = materials.winning_zone_material.clone();
+            commands.entity(winning_zone).remove::<Locked>();
+        }
+        arena.fight = BossFight::Won;
+        return;
+    }
+    for event in living_being_death_events.iter() {
+        if players.get(event.entity).is_ok() {
+            for entity in bosses.iter().chain(gates.iter()) {
+                commands.entity(entity).despawn_recursive();
+            }
+            arena.fight = BossFight::Waiting;
+            return;
+        }
+    }
+}
@@ -1,11 +1,72 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
+use bevy::render::camera::OrthographicProjection;
 use bevy_rapier2d::prelude::*;
 
-use super::{Bullet, GameDirection, LivingBeing, LivingBeingHitEvent, Materials};
+use super::{Bullet, GameCamera, LivingBeing, LivingBeingHitEvent, Materials, Player};
+
+/// Distance after which a bullet vanishes.
+const BULLET_RANGE: f32 = 25.;
+/// Seconds after which a bullet vanishes, for bullets slowed down before reaching their range.
+const BULLET_LIFETIME: f32 = 3.;
+/// Collision group of bullets, which don't collide with each other so that a spread of them can
+/// leave from the same point.
+const BULLET_GROUP: u32 = 0b10;
+pub const PLAYER_GROUP: u32 = 0b100;
+pub const MONSTER_GROUP: u32 = 0b1000;
+
+/// Side a bullet was fired by. Bullets go through the beings of their own faction: collision
+/// groups keep them from touching, and hits are only counted on the other faction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Faction {
+    Player,
+    Monsters,
+}
+
+impl Faction {
+    fn group(&self) -> u32 {
+        match self {
+            Faction::Player => PLAYER_GROUP,
+            Faction::Monsters => MONSTER_GROUP,
+        }
+    }
+}
+
+/// Limits how far and how long a bullet travels.
+#[derive(Component)]
+pub struct BulletLifetime {
+    pub origin: Vec2,
+    pub range: f32,
+    pub timer: Timer,
+}
+
+impl BulletLifetime {
+    /// Ages the bullet, telling whether it is past its range or lifetime, or left the `view`,
+    /// given by its bottom left and top right corners.
+    pub fn is_spent(
+        &mut self,
+        delta: Duration,
+        position: Vec2,
+        view: Option<(Vec2, Vec2)>,
+    ) -> bool {
+        self.timer.tick(delta);
+        let out_of_range = position.distance(self.origin) > self.range;
+        let out_of_view = view.map_or(false, |(min, max)| {
+            position.cmplt(min - Vec2::ONE).any() || position.cmpgt(max + Vec2::ONE).any()
+        });
+        self.timer.finished() || out_of_range || out_of_view
+    }
+}
 
 pub struct BulletFiredEvent {
+    /// Center of the shooter, the bullet appearing a bit further along its direction.
     pub position: Vec2,
-    pub direction: GameDirection,
+    pub direction: Vec2,
+    pub speed: f32,
+    pub damage: u32,
+    pub bounces: u32,
+    pub faction: Faction,
 }
 
 pub fn on_bullet_fired(
@@ -23,25 +84,20 @@ pub fn insert_bullet_at(
     materials: &Res<Materials>,
     options: &BulletFiredEvent,
 ) {
-    let speed = match options.direction {
-        GameDirection::Left => -14.0,
-        _ => 14.0,
-    };
-
-    let x = match options.direction {
-        GameDirection::Left => options.position.x - 1.,
-        _ => options.position.x + 1.,
-    };
+    let direction = options.direction.normalize_or_zero();
+    let origin = options.position + direction;
+    // Bouncing bullets fall, rolling along the ground between bounces.
+    let gravity_scale = if options.bounces > 0 { 1. } else { 0. };
     let rigid_body = RigidBodyBundle {
-        position: Vec2::new(x, options.position.y).into(),
+        position: origin.into(),
         velocity: RigidBodyVelocity {
-            linvel: Vec2::new(speed, 0.0).into(),
+            linvel: (direction * options.speed).into(),
             ..Default::default()
         }.into(),
         mass_properties: RigidBodyMassPropsFlags::ROTATION_LOCKED.into(),
         activation: RigidBodyActivation::cannot_sleep().into(),
         forces: RigidBodyForces {
-            gravity_scale: 0.,
+            gravity_scale,
             ..Default::default()
         }.into(),
         ..Default::default()
@@ -51,6 +107,16 @@ pub fn insert_bullet_at(
         shape: ColliderShape::cuboid(0.25, 0.05).into(),
         flags: ColliderFlags {
             active_events: ActiveEvents::CONTACT_EVENTS,
+            collision_groups: InteractionGroups::new(
+                BULLET_GROUP,
+                !(BULLET_GROUP | options.faction.group()),
+            ),
+            ..Default::default()
+        }.into(),
+        material: ColliderMaterial {
+            friction: 0.,
+            restitution: if options.bounces > 0 { 1. } else { 0. },
+            restitution_combine_rule: CoefficientCombineRule::Max,
             ..Default::default()
         }.into(),
         ..Default::default()
@@ -58,7 +124,10 @@ pub fn insert_bullet_at(
 
     let sprite = SpriteBundle {
         sprite: Sprite {
-            color: materials.bullet_material.clone(),
+            color: match options.faction {
+                Faction::Player => materials.bullet_material.clone(),
+                Faction::Monsters => materials.monster_bullet_material.clone(),
+            },
             custom_size: Vec2::new(0.5, 0.1).into(),
             ..Default::default()
         },
@@ -70,42 +139,165 @@ pub fn insert_bullet_at(
         .insert_bundle(rigid_body)
         .insert_bundle(collider)
         .insert(RigidBodyPositionSync::Discrete)
-        .insert(Bullet);
+        .insert(Bullet {
+            damage: options.damage,
+            bounces: options.bounces,
+            faction: options.faction,
+        })
+        .insert(BulletLifetime {
+            origin,
+            range: BULLET_RANGE,
+            timer: Timer::from_seconds(BULLET_LIFETIME, false),
+        });
 }
 
+/// Despawns bullets past their range or lifetime, or which left the camera's view.
+pub fn despawn_spent_bullets(
+    mut commands: Commands,
+    time: Res<Time>,
+    cameras: Query<(&Transform, &OrthographicProjection), With<GameCamera>>,
+    mut bullets: Query<(Entity, &mut BulletLifetime, &RigidBodyPositionComponent)>,
+) {
+    let view = cameras.get_single().ok().map(|(transform, projection)| {
+        let scale = projection.scale * transform.scale.truncate();
+        let center = transform.translation.truncate();
+        (
+            center + Vec2::new(projection.left, projection.bottom) * scale,
+            center + Vec2::new(projection.right, projection.top) * scale,
+        )
+    });
+    for (bullet, mut lifetime, position) in bullets.iter_mut() {
+        let translation = position.position.translation;
+        let position = Vec2::new(translation.x, translation.y);
+        if lifetime.is_spent(time.delta(), position, view) {
+            commands.entity(bullet).despawn_recursive();
+        }
+    }
+}
+
+/// Despawns bullets hitting something, bouncing ones only once out of bounces or on a being.
 pub fn destroy_bullet_on_contact(
     mut commands: Commands,
-    bullets: Query<Entity, With<Bullet>>,
+    mut bullets: Query<(Entity, &mut Bullet)>,
+    living_beings: Query<(), With<LivingBeing>>,
     mut contact_events: EventReader<ContactEvent>,
 ) {
     for contact_event in contact_events.iter() {
         if let ContactEvent::Started(h1, h2) = contact_event {
-            for bullet in bullets.iter() {
-                if h1.entity() == bullet || h2.entity() == bullet {
-                    commands.entity(bullet).despawn_recursive();
+            for (bullet_entity, mut bullet) in bullets.iter_mut() {
+                let other = if h1.entity() == bullet_entity {
+                    h2.entity()
+                } else if h2.entity() == bullet_entity {
+                    h1.entity()
+                } else {
+                    continue;
+                };
+                if bullet.bounces > 0 && living_beings.get(other).is_err() {
+                    bullet.bounces -= 1;
+                } else {
+                    commands.entity(bullet_entity).despawn_recursive();
                 }
             }
         }
     }
 }
 
+/// Hurts beings of the other faction hit by bullets.
 pub fn kill_on_contact(
     mut send_living_being_hit: EventWriter<LivingBeingHitEvent>,
-    bullets: Query<Entity, With<Bullet>>,
-    living_being: Query<Entity, With<LivingBeing>>,
+    bullets: Query<(Entity, &Bullet, &RigidBodyVelocityComponent)>,
+    living_being: Query<(Entity, Option<&Player>), With<LivingBeing>>,
     mut contact_events: EventReader<ContactEvent>,
 ) {
     for contact_event in contact_events.iter() {
         if let ContactEvent::Started(h1, h2) = contact_event {
-            for bullet in bullets.iter() {
-                for enemy in living_being.iter() {
+            for (bullet, Bullet { damage, faction, .. }, velocity) in bullets.iter() {
+                for (enemy, player) in living_being.iter() {
+                    let enemy_faction = if player.is_some() {
+                        Faction::Player
+                    } else {
+                        Faction::Monsters
+                    };
+                    if enemy_faction == *faction {
+                        continue;
+                    }
                     if (h1.entity() == bullet && h2.entity() == enemy)
                         || (h1.entity() == enemy && h2.entity() == bullet)
                     {
-                        send_living_being_hit.send(LivingBeingHitEvent { entity: enemy });
+                        let direction =
+                            Vec2::new(velocity.linvel.x, velocity.linvel.y).normalize_or_zero();
+                        send_living_being_hit.send(LivingBeingHitEvent {
+                            entity: enemy,
+                            damage: *damage,
+                            knockback: direction * 6. + Vec2::new(0., 4.),
+                        });
                     }
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME_TIME: f32 = 1. / 60.;
+    const FIRING_FRAMES: u32 = 600;
+
+    /// Fires one bullet per frame from the origin at `velocity`, then stops firing, checking that
+    /// the bullets alive never exceed `cap` and are all gone after the lifetime.
+    fn assert_bounded(velocity: Vec2, view: Option<(Vec2, Vec2)>, cap: usize) {
+        let delta = Duration::from_secs_f32(FRAME_TIME);
+        let mut bullets: Vec<(BulletLifetime, Vec2)> = Vec::new();
+        let frames_to_expire = (BULLET_LIFETIME / FRAME_TIME) as u32 + 2;
+        for frame in 0..FIRING_FRAMES + frames_to_expire {
+            if frame < FIRING_FRAMES {
+                bullets.push((
+                    BulletLifetime {
+                        origin: Vec2::ZERO,
+                        range: BULLET_RANGE,
+                        timer: Timer::from_seconds(BULLET_LIFETIME, false),
+                    },
+                    Vec2::ZERO,
+                ));
+            }
+            bullets = bullets
+                .into_iter()
+                .map(|(lifetime, position)| (lifetime, position + velocity * FRAME_TIME))
+                .filter_map(|(mut lifetime, position)| {
+                    if lifetime.is_spent(delta, position, view) {
+                        None
+                    } else {
+                        Some((lifetime, position))
+                    }
+                })
+                .collect();
+            assert!(bullets.len() <= cap, "{} bullets alive", bullets.len());
+        }
+        assert!(bullets.is_empty());
+    }
+
+    #[test]
+    fn slow_bullets_vanish_after_their_lifetime() {
+        let cap = (BULLET_LIFETIME / FRAME_TIME) as usize + 1;
+        assert_bounded(Vec2::new(1., 0.), None, cap);
+    }
+
+    #[test]
+    fn fast_bullets_vanish_past_their_range() {
+        let speed = 20.;
+        let cap = (BULLET_RANGE / speed / FRAME_TIME) as usize + 1;
+        assert!(cap < (BULLET_LIFETIME / FRAME_TIME) as usize);
+        assert_bounded(Vec2::new(speed, 0.), None, cap);
+    }
+
+    #[test]
+    fn bullets_vanish_out_of_view() {
+        let speed = 10.;
+        let view = (Vec2::new(-5., -5.), Vec2::new(5., 5.));
+        // Bullets vanish a tile past the edge of the view.
+        let cap = (6. / speed / FRAME_TIME) as usize + 1;
+        assert_bounded(Vec2::new(speed, 0.), Some(view), cap);
+    }
+}
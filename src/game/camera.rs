@@ -1,9 +1,13 @@
 use bevy::{
     math::Vec3,
-    prelude::OrthographicCameraBundle,
+    prelude::{Component, OrthographicCameraBundle},
     render::camera::{DepthCalculation, OrthographicProjection, ScalingMode},
 };
 
+/// The camera looking at the level, as opposed to the UI camera drawing the HUD.
+#[derive(Component)]
+pub struct GameCamera;
+
 pub fn new_camera_2d() -> OrthographicCameraBundle {
     let far = 1000.0;
     let mut camera = OrthographicCameraBundle::new_2d();
@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{Materials, Player};
+
+#[derive(Component)]
+pub struct Checkpoint {
+    pub activated: bool,
+}
+
+/// Where the player comes back after losing a life.
+pub struct RespawnPoint(pub Vec2);
+
+pub fn insert_checkpoint_at(
+    commands: &mut Commands,
+    x: usize,
+    y: usize,
+    materials: &Res<Materials>,
+) {
+    let rigid_body = RigidBodyBundle {
+        position: Vec2::new(x as f32, y as f32 + 0.25).into(),
+        body_type: RigidBodyType::Static.into(),
+        ..Default::default()
+    };
+
+    let collider = ColliderBundle {
+        collider_type: ColliderType::Sensor.into(),
+        shape: ColliderShape::cuboid(0.5, 1.).into(),
+        flags: ColliderFlags {
+            active_events: ActiveEvents::INTERSECTION_EVENTS,
+            ..Default::default()
+        }
+        .into(),
+        ..Default::default()
+    };
+
+    let sprite = SpriteBundle {
+        sprite: Sprite {
+            color: materials.checkpoint_material.clone(),
+            custom_size: Vec2::new(0.2, 1.5).into(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    commands
+        .spawn_bundle(sprite)
+        .insert_bundle(rigid_body)
+        .insert_bundle(collider)
+        .insert(RigidBodyPositionSync::Discrete)
+        .insert(Checkpoint { activated: false });
+}
+
+pub fn activate_checkpoints(
+    materials: Res<Materials>,
+    mut respawn_point: ResMut<RespawnPoint>,
+    players: Query<Entity, With<Player>>,
+    mut checkpoints: Query<(&mut Checkpoint, &mut Sprite, &RigidBodyPositionComponent)>,
+    mut intersection_events: EventReader<IntersectionEvent>,
+) {
+    for event in intersection_events.iter() {
+        if !event.intersecting {
+            continue;
+        }
+        let (e1, e2) = (event.collider1.entity(), event.collider2.entity());
+        for player in players.iter() {
+            let checkpoint = if e1 == player {
+                e2
+            } else if e2 == player {
+                e1
+            } else {
+                continue;
+            };
+            if let Ok((mut checkpoint, mut sprite, position)) = checkpoints.get_mut(checkpoint) {
+                if !checkpoint.activated {
+                    checkpoint.activated = true;
+                    sprite.color = materials.checkpoint_active_material.clone();
+                    let translation = position.position.translation;
+                    respawn_point.0 = Vec2::new(translation.x, translation.y + 0.5);
+                }
+            }
+        }
+    }
+}
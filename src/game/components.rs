@@ -1,11 +1,29 @@
 use bevy::prelude::{Component, Color};
 
+use super::{Faction, MonsterBehaviour};
+
 pub struct Materials {
     pub player_material: Color,
     pub floor_material: Color,
-    pub monster_material: Color,
+    pub platform_material: Color,
     pub bullet_material: Color,
+    pub monster_bullet_material: Color,
     pub winning_zone_material: Color,
+    pub boss_material: Color,
+    pub boss_enraged_material: Color,
+    /// Arena gates and the winning zone while they are locked.
+    pub gate_material: Color,
+    pub boss_health_material: Color,
+    pub checkpoint_material: Color,
+    pub checkpoint_active_material: Color,
+    pub hud_background_material: Color,
+    pub progress_background_material: Color,
+    pub progress_material: Color,
+    pub ammo_pickup_material: Color,
+    pub health_pickup_material: Color,
+    pub weapon_pickup_material: Color,
+    pub boost_pickup_material: Color,
+    pub shield_pickup_material: Color,
 }
 
 #[derive(Copy, Clone)]
@@ -25,18 +43,38 @@ pub struct Enemy;
 
 #[derive(Component)]
 pub struct Monster {
+    /// Name of the monster's definition in the monster table.
+    pub name: String,
     pub speed: f32,
     pub facing_direction: GameDirection,
+    pub behaviour: MonsterBehaviour,
 }
 
 #[derive(Component)]
-pub struct Bullet;
+pub struct Bullet {
+    pub damage: u32,
+    /// Walls the bullet can still bounce off.
+    pub bounces: u32,
+    pub faction: Faction,
+}
 
 #[derive(Component)]
 pub struct Jumper {
     pub jump_impulse: f32,
-    pub is_jumping: bool,
+    /// Seconds after walking off a ledge during which jumping is still allowed.
+    pub coyote_time: f32,
+    /// Seconds a jump request is remembered while in the air.
+    pub jump_buffer: f32,
+    /// Multiplier applied to the upward velocity when the jump is released early.
+    pub jump_cut: f32,
+    pub time_since_grounded: f32,
+    pub time_since_jump_request: f32,
+    pub is_holding_jump: bool,
+    pub is_rising: bool,
 }
 
 #[derive(Component)]
 pub struct WinningZone;
+
+#[derive(Component)]
+pub struct OneWayPlatform;
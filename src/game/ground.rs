@@ -0,0 +1,138 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{Bullet, Jumper};
+
+/// Present on jumpers standing on something they can jump from.
+#[derive(Component)]
+pub struct Grounded;
+
+/// How far below their feet jumpers look for ground.
+const GROUND_DISTANCE: f32 = 0.1;
+/// Half height of the box cast from the jumpers' feet.
+const FEET_HALF_HEIGHT: f32 = 0.05;
+/// How much narrower than the jumper the box cast from its feet is, on each side.
+const FEET_INSET: f32 = 0.15;
+
+/// Casts a thin box, narrower than the jumper, down from its feet so that touching walls or
+/// hitting things from below doesn't count as standing on the ground. The box is sized from
+/// each jumper's collider, so that jumpers of any size find the ground.
+pub fn detect_ground(
+    mut commands: Commands,
+    query_pipeline: Res<QueryPipeline>,
+    collider_query: QueryPipelineColliderComponentsQuery,
+    jumpers: Query<
+        (
+            Entity,
+            &RigidBodyPositionComponent,
+            &RigidBodyVelocityComponent,
+            &ColliderShapeComponent,
+            Option<&Grounded>,
+        ),
+        With<Jumper>,
+    >,
+    collider_types: Query<&ColliderTypeComponent>,
+    bullets: Query<(), With<Bullet>>,
+) {
+    let colliders = QueryPipelineColliderComponentsSet(&collider_query);
+    for (entity, position, velocity, shape, grounded) in jumpers.iter() {
+        let half_extents = shape.compute_local_aabb().half_extents();
+        let feet = Cuboid::new(Vector::new(
+            (half_extents.x - FEET_INSET).max(FEET_HALF_HEIGHT),
+            FEET_HALF_HEIGHT,
+        ));
+        let translation = position.position.translation;
+        let feet_position = Isometry::translation(
+            translation.x,
+            translation.y - half_extents.y + FEET_HALF_HEIGHT,
+        );
+        let is_ground = |handle: ColliderHandle| {
+            let other = handle.entity();
+            other != entity
+                && bullets.get(other).is_err()
+                && collider_types
+                    .get(other)
+                    .map_or(false, |collider_type| **collider_type == ColliderType::Solid)
+        };
+        let hit = query_pipeline.cast_shape(
+            &colliders,
+            &feet_position,
+            &Vector::new(0., -1.),
+            &feet,
+            GROUND_DISTANCE,
+            InteractionGroups::all(),
+            Some(&is_ground),
+        );
+        let is_grounded = hit.is_some() && velocity.linvel.y <= 0.1;
+
+        match (is_grounded, grounded.is_some()) {
+            (true, false) => {
+                commands.entity(entity).insert(Grounded);
+            }
+            (false, true) => {
+                commands.entity(entity).remove::<Grounded>();
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::BOSS_SIZE;
+    use super::*;
+
+    /// Lets a square jumper of the given size settle on a floor, telling whether it is grounded.
+    fn is_grounded_on_floor(size: f32) -> bool {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+            .add_system(detect_ground.system());
+
+        app.world
+            .spawn()
+            .insert_bundle(RigidBodyBundle {
+                body_type: RigidBodyType::Static.into(),
+                ..Default::default()
+            })
+            .insert_bundle(ColliderBundle {
+                shape: ColliderShape::cuboid(5., 0.5).into(),
+                ..Default::default()
+            });
+        let half_size = size / 2.;
+        let jumper = app
+            .world
+            .spawn()
+            .insert_bundle(RigidBodyBundle {
+                position: Vec2::new(0., 0.5 + half_size).into(),
+                mass_properties: RigidBodyMassPropsFlags::ROTATION_LOCKED.into(),
+                ..Default::default()
+            })
+            .insert_bundle(ColliderBundle {
+                shape: ColliderShape::round_cuboid(half_size - 0.1, half_size - 0.1, 0.1).into(),
+                ..Default::default()
+            })
+            .insert(Jumper::new(10., 0.05, 0.3, 1.))
+            .id();
+
+        for _ in 0..10 {
+            app.update();
+        }
+        app.world.get::<Grounded>(jumper).is_some()
+    }
+
+    #[test]
+    fn player_sized_jumper_is_grounded() {
+        assert!(is_grounded_on_floor(0.9));
+    }
+
+    #[test]
+    fn large_monster_is_grounded() {
+        assert!(is_grounded_on_floor(1.2));
+    }
+
+    #[test]
+    fn boss_is_grounded() {
+        assert!(is_grounded_on_floor(BOSS_SIZE));
+    }
+}
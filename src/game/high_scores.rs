@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+/// Entries kept for each seed.
+const ENTRIES_PER_SEED: usize = 5;
+const HIGH_SCORES_KEY: &str = "high_scores";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighScore {
+    pub name: String,
+    pub seed: u64,
+    pub score: u32,
+    /// Index of the level the run ended on.
+    pub level: usize,
+    /// Seconds played over the whole run.
+    pub time: f32,
+}
+
+/// Best runs, sorted by decreasing score.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    pub entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    /// Loads the saved high scores, starting from an empty table.
+    pub fn load() -> Self {
+        storage::load_ron(HIGH_SCORES_KEY, "high scores").unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        storage::save_ron(HIGH_SCORES_KEY, "high scores", self);
+    }
+
+    /// Best entries for a seed, best first.
+    pub fn top(&self, seed: u64) -> Vec<&HighScore> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.seed == seed)
+            .take(ENTRIES_PER_SEED)
+            .collect()
+    }
+
+    /// Seeds having entries, the one with the best score first.
+    pub fn seeds(&self) -> Vec<u64> {
+        let mut seeds: Vec<u64> = Vec::new();
+        for entry in self.entries.iter() {
+            if !seeds.contains(&entry.seed) {
+                seeds.push(entry.seed);
+            }
+        }
+        seeds
+    }
+
+    pub fn qualifies(&self, seed: u64, score: u32) -> bool {
+        let top = self.top(seed);
+        score > 0 && (top.len() < ENTRIES_PER_SEED || top.iter().any(|entry| score > entry.score))
+    }
+
+    pub fn insert(&mut self, entry: HighScore) {
+        let position = self
+            .entries
+            .iter()
+            .position(|other| entry.score > other.score)
+            .unwrap_or(self.entries.len());
+        let seed = entry.seed;
+        self.entries.insert(position, entry);
+
+        let mut kept = 0;
+        self.entries.retain(|entry| {
+            if entry.seed != seed {
+                return true;
+            }
+            kept += 1;
+            kept <= ENTRIES_PER_SEED
+        });
+    }
+}
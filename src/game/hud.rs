@@ -0,0 +1,220 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::super::AppState;
+use super::{
+    Boss, Health, LevelProgress, LevelTime, Materials, Player, Score, Weapon, WinningZone,
+};
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::InGame).with_system(spawn_hud.system()))
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(update_hud_texts.system())
+                    .with_system(update_progress_bar.system())
+                    .with_system(update_boss_health_bar.system()),
+            );
+    }
+}
+
+#[derive(Component, Copy, Clone)]
+enum HudText {
+    Score,
+    Lives,
+    Level,
+    Time,
+    Ammo,
+}
+
+/// The filled part of the bar showing how far the player is from the winning zone.
+#[derive(Component)]
+struct ProgressBar;
+
+/// The bar showing the boss's health, only displayed during the fight.
+#[derive(Component)]
+struct BossHealthBar;
+
+/// The filled part of the boss's health bar.
+#[derive(Component)]
+struct BossHealthFill;
+
+fn hud_text(asset_server: &Res<AssetServer>) -> TextBundle {
+    TextBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(6.0)),
+            ..Default::default()
+        },
+        text: Text::with_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                font_size: 18.0,
+                color: Color::WHITE,
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    }
+}
+
+/// Spawns the HUD along with its UI camera, both despawned with the level on state exit.
+fn spawn_hud(mut commands: Commands, asset_server: Res<AssetServer>, materials: Res<Materials>) {
+    commands.spawn_bundle(UiCameraBundle::default());
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(0.0),
+                    top: Val::Px(0.0),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            color: materials.hud_background_material.into(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Auto),
+                        justify_content: JustifyContent::SpaceBetween,
+                        ..Default::default()
+                    },
+                    color: Color::NONE.into(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for kind in [
+                        HudText::Score,
+                        HudText::Lives,
+                        HudText::Level,
+                        HudText::Time,
+                        HudText::Ammo,
+                    ] {
+                        parent.spawn_bundle(hud_text(&asset_server)).insert(kind);
+                    }
+                });
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Px(6.0)),
+                        ..Default::default()
+                    },
+                    color: materials.progress_background_material.into(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                                ..Default::default()
+                            },
+                            color: materials.progress_material.into(),
+                            ..Default::default()
+                        })
+                        .insert(ProgressBar);
+                });
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Px(10.0)),
+                        display: Display::None,
+                        ..Default::default()
+                    },
+                    color: materials.progress_background_material.into(),
+                    ..Default::default()
+                })
+                .insert(BossHealthBar)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                                ..Default::default()
+                            },
+                            color: materials.boss_health_material.into(),
+                            ..Default::default()
+                        })
+                        .insert(BossHealthFill);
+                });
+        });
+}
+
+fn update_hud_texts(
+    progress: Res<LevelProgress>,
+    level_time: Res<LevelTime>,
+    score: Res<Score>,
+    players: Query<(&Health, &Weapon), With<Player>>,
+    mut texts: Query<(&HudText, &mut Text)>,
+) {
+    let player = players.get_single().ok();
+    let health = player.map_or(0, |(health, _)| health.current);
+    let seconds = level_time.elapsed as u32;
+    for (kind, mut text) in texts.iter_mut() {
+        let value = match kind {
+            HudText::Score => format!("Score {}", progress.score + score.total()),
+            HudText::Lives => format!("Lives {} HP {}", progress.lives, health),
+            HudText::Level => format!("Level {}", progress.level + 1),
+            HudText::Time => format!("{}:{:02}", seconds / 60, seconds % 60),
+            HudText::Ammo => player.map_or_else(String::new, |(_, weapon)| {
+                format!("{} {}", weapon.kind.label(), weapon.ammo_label())
+            }),
+        };
+        // Avoid laying the text out again every frame.
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+fn update_progress_bar(
+    players: Query<&RigidBodyPositionComponent, With<Player>>,
+    winning_zones: Query<&RigidBodyPositionComponent, With<WinningZone>>,
+    mut bars: Query<&mut Style, With<ProgressBar>>,
+) {
+    if let (Ok(player), Ok(winning_zone)) = (players.get_single(), winning_zones.get_single()) {
+        let goal = winning_zone.position.translation.x;
+        let fraction = (player.position.translation.x / goal).clamp(0., 1.);
+        let width = Val::Percent(fraction * 100.);
+        for mut style in bars.iter_mut() {
+            if style.size.width != width {
+                style.size.width = width;
+            }
+        }
+    }
+}
+
+fn update_boss_health_bar(
+    bosses: Query<&Health, With<Boss>>,
+    mut bars: Query<&mut Style, (With<BossHealthBar>, Without<BossHealthFill>)>,
+    mut fills: Query<&mut Style, (With<BossHealthFill>, Without<BossHealthBar>)>,
+) {
+    let boss = bosses.get_single().ok();
+    let display = if boss.is_some() {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    for mut style in bars.iter_mut() {
+        if style.display != display {
+            style.display = display;
+        }
+    }
+    if let Some(health) = boss {
+        let width = Val::Percent(health.current as f32 / health.max as f32 * 100.);
+        for mut style in fills.iter_mut() {
+            if style.size.width != width {
+                style.size.width = width;
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{Grounded, Jumper};
+
+impl Jumper {
+    pub fn new(jump_impulse: f32, coyote_time: f32, jump_buffer: f32, jump_cut: f32) -> Self {
+        Jumper {
+            jump_impulse,
+            coyote_time,
+            jump_buffer,
+            jump_cut,
+            time_since_grounded: f32::INFINITY,
+            time_since_jump_request: f32::INFINITY,
+            is_holding_jump: true,
+            is_rising: false,
+        }
+    }
+
+    /// Jumps as soon as possible, if that happens within `jump_buffer` seconds.
+    pub fn request_jump(&mut self) {
+        self.time_since_jump_request = 0.;
+    }
+
+    fn can_jump(&self) -> bool {
+        self.time_since_grounded <= self.coyote_time
+            && self.time_since_jump_request <= self.jump_buffer
+    }
+}
+
+pub fn apply_jumps(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut jumpers: Query<(
+        Entity,
+        &mut Jumper,
+        &mut RigidBodyVelocityComponent,
+        Option<&Grounded>,
+    )>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut jumper, mut velocity, grounded) in jumpers.iter_mut() {
+        if grounded.is_some() && !jumper.is_rising {
+            jumper.time_since_grounded = 0.;
+        } else {
+            jumper.time_since_grounded += delta;
+        }
+        jumper.time_since_jump_request += delta;
+
+        if jumper.can_jump() {
+            velocity.linvel = Vec2::new(0., jumper.jump_impulse).into();
+            jumper.time_since_grounded = f32::INFINITY;
+            jumper.time_since_jump_request = f32::INFINITY;
+            jumper.is_rising = true;
+            commands.entity(entity).remove::<Grounded>();
+        } else if jumper.is_rising {
+            if velocity.linvel.y > 0. && !jumper.is_holding_jump {
+                velocity.linvel.y *= jumper.jump_cut;
+                jumper.is_rising = false;
+            } else if velocity.linvel.y <= 0. {
+                jumper.is_rising = false;
+            }
+        }
+    }
+}
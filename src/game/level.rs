@@ -0,0 +1,56 @@
+use bevy::{prelude::*, reflect::TypeUuid};
+use serde::Deserialize;
+
+use super::{PickupKind, RonAsset, TileGrid};
+use crate::command_line_argument;
+
+/// A playable level, either generated from a `LevelSeed` or loaded from a `.level.ron` file.
+/// Positions are in tiles, `x` being the column and `y` the height above the ground.
+#[derive(Debug, Clone, PartialEq, Deserialize, TypeUuid)]
+#[uuid = "feaba536-9792-4492-899d-9a7eec69fc9a"]
+pub struct Level {
+    pub name: String,
+    /// Time in seconds a good run should take.
+    pub par_time: f32,
+    pub tiles: TileGrid,
+    pub enemies: Vec<(usize, usize)>,
+    #[serde(default)]
+    pub checkpoints: Vec<(usize, usize)>,
+    #[serde(default)]
+    pub pickups: Vec<(usize, usize, PickupKind)>,
+    pub player_start: (f32, f32),
+    pub winning_zone: f32,
+    /// First column of the arena where a boss guards the winning zone, if any.
+    #[serde(default)]
+    pub boss_arena: Option<usize>,
+}
+
+impl RonAsset for Level {
+    const EXTENSIONS: &'static [&'static str] = &["level.ron"];
+}
+
+/// Hand-authored level played in the current run instead of a generated one, passed with
+/// `--level` or continued from a saved run.
+pub struct LevelFile(pub Handle<Level>);
+
+/// The level passed with `--level`, which continuing a saved run may replace.
+pub struct CommandLineLevelFile(pub Option<Handle<Level>>);
+
+pub fn load_level_file(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let level_file = command_line_argument("--level").map(|path| asset_server.load(path.as_str()));
+    if let Some(handle) = &level_file {
+        commands.insert_resource(LevelFile(handle.clone()));
+    }
+    commands.insert_resource(CommandLineLevelFile(level_file));
+}
+
+/// Puts back the level passed with `--level` for the next run, once back to the main menu.
+pub fn restore_level_file(
+    mut commands: Commands,
+    command_line_level_file: Res<CommandLineLevelFile>,
+) {
+    match &command_line_level_file.0 {
+        Some(handle) => commands.insert_resource(LevelFile(handle.clone())),
+        None => commands.remove_resource::<LevelFile>(),
+    }
+}
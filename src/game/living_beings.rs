@@ -1,15 +1,41 @@
 use bevy::prelude::*;
-use bevy_rapier2d::prelude::{RigidBodyPositionComponent};
+use bevy_rapier2d::prelude::{Isometry, RigidBodyPositionComponent, RigidBodyVelocityComponent};
 
 use crate::AppState;
 
-use super::Player;
+use super::{
+    Boss, BossDefeatedEvent, Enemy, LevelProgress, Player, RespawnPoint, Score, ScoreEvent,
+};
 
 #[derive(Component)]
 pub struct LivingBeing;
 
+#[derive(Component)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+    /// Seconds during which further hits are ignored after taking damage.
+    pub invulnerability: f32,
+}
+
+impl Health {
+    pub fn new(max: u32, invulnerability: f32) -> Self {
+        Health {
+            current: max,
+            max,
+            invulnerability,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Invulnerable(pub Timer);
+
 pub struct LivingBeingHitEvent {
     pub entity: Entity,
+    pub damage: u32,
+    /// Velocity given to the entity when it is hit.
+    pub knockback: Vec2,
 }
 
 pub struct LivingBeingDeathEvent {
@@ -17,29 +43,126 @@ pub struct LivingBeingDeathEvent {
 }
 
 pub fn on_living_being_hit(
+    mut commands: Commands,
     mut living_being_hit_events: EventReader<LivingBeingHitEvent>,
     mut send_living_being_death: EventWriter<LivingBeingDeathEvent>,
+    mut score: ResMut<Score>,
+    mut living_beings: Query<(
+        &mut Health,
+        &mut RigidBodyVelocityComponent,
+        Option<&Invulnerable>,
+    )>,
+    players: Query<(), With<Player>>,
 ) {
+    // Invulnerable is only inserted at the end of the stage: count one hit per frame.
+    let mut hit_this_frame: Vec<Entity> = Vec::new();
     for event in living_being_hit_events.iter() {
-        send_living_being_death.send(LivingBeingDeathEvent {
-            entity: event.entity,
-        })
+        if hit_this_frame.contains(&event.entity) {
+            continue;
+        }
+        if let Ok((mut health, mut velocity, invulnerable)) = living_beings.get_mut(event.entity) {
+            if invulnerable.is_some() || health.current == 0 {
+                continue;
+            }
+            hit_this_frame.push(event.entity);
+            if players.get(event.entity).is_ok() {
+                score.damage_taken = true;
+            }
+            health.current = health.current.saturating_sub(event.damage);
+            velocity.linvel = event.knockback.into();
+            if health.current == 0 {
+                send_living_being_death.send(LivingBeingDeathEvent {
+                    entity: event.entity,
+                })
+            } else {
+                commands
+                    .entity(event.entity)
+                    .insert(Invulnerable(Timer::from_seconds(health.invulnerability, false)));
+            }
+        }
+    }
+}
+
+/// Makes invulnerable entities blink until their invulnerability wears off.
+pub fn invulnerability_countdown(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut invulnerables: Query<(Entity, &mut Invulnerable, &mut Sprite)>,
+) {
+    for (entity, mut invulnerable, mut sprite) in invulnerables.iter_mut() {
+        invulnerable.0.tick(time.delta());
+        if invulnerable.0.finished() {
+            sprite.color.set_a(1.);
+            commands.entity(entity).remove::<Invulnerable>();
+        } else {
+            let blink_on = (invulnerable.0.elapsed_secs() * 10.) as u32 % 2 == 0;
+            sprite.color.set_a(if blink_on { 1. } else { 0.3 });
+        }
     }
 }
 
 pub fn on_living_being_dead(
     mut living_being_death_events: EventReader<LivingBeingDeathEvent>,
-    player_query: Query<Entity, With<Player>>,
+    mut players: Query<
+        (
+            Entity,
+            &mut Health,
+            &mut RigidBodyPositionComponent,
+            &mut RigidBodyVelocityComponent,
+        ),
+        With<Player>,
+    >,
     mut commands: Commands,
     mut app_state: ResMut<State<AppState>>,
+    mut progress: ResMut<LevelProgress>,
+    respawn_point: Res<RespawnPoint>,
+    enemies: Query<(), With<Enemy>>,
+    bosses: Query<(), With<Boss>>,
+    mut score: ResMut<Score>,
+    mut send_score: EventWriter<ScoreEvent>,
+    mut send_boss_defeated: EventWriter<BossDefeatedEvent>,
 ) {
+    let mut player_died = false;
+    // A being can die of several causes in the same frame.
+    let mut dead: Vec<Entity> = Vec::new();
     for event in living_being_death_events.iter() {
-        let player_id = player_query.get_single();
-        commands.entity(event.entity).despawn_recursive();
-        match player_id {
-            Ok(player) if event.entity == player => app_state.set(AppState::GameOver).unwrap(),
-            _ => (),
-        };
+        if players.get(event.entity).is_ok() {
+            player_died = true;
+        } else if !dead.contains(&event.entity) {
+            dead.push(event.entity);
+            if enemies.get(event.entity).is_ok() {
+                send_score.send(ScoreEvent::EnemyKilled);
+            }
+            if bosses.get(event.entity).is_ok() {
+                send_score.send(ScoreEvent::BossDefeated);
+                send_boss_defeated.send(BossDefeatedEvent);
+            }
+            commands.entity(event.entity).despawn_recursive();
+        }
+    }
+    if !player_died {
+        return;
+    }
+
+    score.damage_taken = true;
+    progress.lives = progress.lives.saturating_sub(1);
+    if progress.lives == 0 {
+        // Losing the last life wins over another transition queued on this frame, such as
+        // reaching the winning zone.
+        app_state
+            .overwrite_set(AppState::GameOver)
+            .expect("Couldn't switch state to GameOver");
+        return;
+    }
+    for (player, mut health, mut position, mut velocity) in players.iter_mut() {
+        let respawn = Isometry::translation(respawn_point.0.x, respawn_point.0.y);
+        position.position = respawn;
+        position.next_position = respawn;
+        velocity.linvel = Vec2::ZERO.into();
+        health.current = health.max;
+        commands
+            .entity(player)
+            .insert(Invulnerable(Timer::from_seconds(health.invulnerability, false)));
     }
 }
 
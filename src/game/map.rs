@@ -1,55 +1,355 @@
-use super::{components::Materials, insert_monster_at, WinningZone};
+use super::{
+    components::Materials, insert_boss_arena, insert_checkpoint_at, insert_monster_at,
+    insert_pickup_at, insert_player_at, validate_level, BossArena, Difficulty, Level, LevelFile,
+    LevelProgress, LevelTime, Locked, MonsterTable, MonsterTableFile, OneWayPlatform, PickupKind,
+    RespawnPoint, ResumedRun, Tile, TileGrid, TileRun, WeaponKind, WinningZone,
+};
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
+use bevy_rapier2d::rapier::data::ComponentSetOption;
 use rand::prelude::*;
 
-pub fn spawn_floor(mut commands: Commands, materials: Res<Materials>) {
-    let world = create_world(150);
-    add_sprites(&mut commands, &materials, &world);
-    add_colliders(&world, &mut commands);
+use crate::command_line_argument;
 
-    add_enemies(&mut commands, &world, &materials);
-    add_winning_zone(&mut commands, &materials, 150.)
+pub struct LevelSeed {
+    pub value: u64,
+    /// Set when the seed was given on the command line, so it survives new games.
+    pub fixed: bool,
 }
 
-fn add_sprites(commands: &mut Commands, materials: &Res<Materials>, world: &Vec<usize>) {
-    world.iter().enumerate().for_each(|(x, height)| {
-        add_tile(commands, materials, x as f32, *height);
-    });
+impl LevelSeed {
+    pub fn from_command_line() -> Self {
+        match command_line_argument("--seed").and_then(|seed| seed.parse().ok()) {
+            Some(value) => LevelSeed { value, fixed: true },
+            None => LevelSeed::random(),
+        }
+    }
+
+    pub fn random() -> Self {
+        LevelSeed {
+            value: thread_rng().gen(),
+            fixed: false,
+        }
+    }
+}
+
+pub fn reroll_level_seed(mut seed: ResMut<LevelSeed>) {
+    if !seed.fixed {
+        *seed = LevelSeed::random();
+    }
+}
+
+pub fn spawn_floor(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    seed: Res<LevelSeed>,
+    progress: Res<LevelProgress>,
+    level_file: Option<Res<LevelFile>>,
+    levels: Res<Assets<Level>>,
+    monster_table_file: Option<Res<MonsterTableFile>>,
+    monster_tables: Res<Assets<MonsterTable>>,
+    mut level_time: ResMut<LevelTime>,
+    resumed: Option<Res<ResumedRun>>,
+) {
+    let difficulty = progress.difficulty();
+    let level_seed = progress.level_seed(&seed);
+    let monster_table = monster_table_file
+        .and_then(|file| monster_tables.get(&file.0).cloned())
+        .unwrap_or_default();
+    let level = match level_file.and_then(|file| levels.get(&file.0).cloned()) {
+        Some(level) => {
+            if let Err(error) = validate_level(&level) {
+                warn!("Level {} is not winnable: {:?}", level.name, error);
+            }
+            level
+        }
+        None => generate_level(level_seed, &difficulty),
+    };
+    level_time.par = level.par_time;
+    add_sprites(&mut commands, &materials, &level.tiles);
+    add_colliders(&level.tiles, &mut commands);
+
+    add_checkpoints(&mut commands, &level.checkpoints, &materials);
+    match resumed {
+        Some(resumed) => {
+            let run = &resumed.0;
+            for (x, y, name) in run.monsters.iter() {
+                let definition = monster_table.get(name).cloned().unwrap_or_default();
+                // Saved positions are centers, not the ground the monster stands on.
+                let y = *y - (definition.size.1 - 0.9) / 2.;
+                insert_monster_at(&mut commands, *x, y, &definition);
+            }
+            for (x, y, kind) in run.pickups.iter() {
+                insert_pickup_at(&mut commands, *x, *y, *kind, &materials);
+            }
+            let (x, y) = run.respawn_point;
+            commands.insert_resource(RespawnPoint(Vec2::new(x, y)));
+            let (x, y) = run.player_position;
+            insert_player_at(&mut commands, &materials, x, y);
+            commands.remove_resource::<ResumedRun>();
+        }
+        None => {
+            let mut rng = StdRng::seed_from_u64(level_seed);
+            add_enemies(
+                &mut commands,
+                &level.enemies,
+                &monster_table,
+                &difficulty,
+                &mut rng,
+            );
+            add_pickups(&mut commands, &level.pickups, &materials);
+            commands.insert_resource(RespawnPoint(Vec2::new(
+                level.player_start.0,
+                level.player_start.1,
+            )));
+            insert_player_at(
+                &mut commands,
+                &materials,
+                level.player_start.0,
+                level.player_start.1,
+            );
+        }
+    }
+    match level.boss_arena {
+        Some(from) => insert_boss_arena(&mut commands, &level, from),
+        None => commands.remove_resource::<BossArena>(),
+    }
+    add_winning_zone(
+        &mut commands,
+        &materials,
+        level.winning_zone,
+        level.boss_arena.is_some(),
+    )
+}
+
+const GENERATION_ATTEMPTS: usize = 10;
+/// Width of the flat arena ending boss levels.
+const ARENA_WIDTH: usize = 20;
+
+/// Generates levels from the seed until one is winnable, repairing the last one if none is.
+pub fn generate_level(seed: u64, difficulty: &Difficulty) -> Level {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut level = create_level(seed, difficulty, &mut rng);
+    for _ in 1..GENERATION_ATTEMPTS {
+        if validate_level(&level).is_ok() {
+            return level;
+        }
+        level = create_level(seed, difficulty, &mut rng);
+    }
+    if validate_level(&level).is_err() {
+        repair_level(&mut level);
+    }
+    level
+}
+
+fn create_level(seed: u64, difficulty: &Difficulty, rng: &mut StdRng) -> Level {
+    let width = difficulty.width;
+    let mut heights = create_world(width, difficulty, rng);
+    add_pits(&mut heights, difficulty, rng);
+    let boss_arena = if difficulty.boss {
+        Some(flatten_boss_arena(&mut heights))
+    } else {
+        None
+    };
+    let mut tiles = TileGrid::from_heights(&heights, 6);
+    add_platforms(&mut tiles, &heights, difficulty, rng);
+    add_overhangs(&mut tiles, &heights, difficulty, rng);
+    let mut enemies = generate_enemies(&heights, difficulty, rng);
+    let mut checkpoints = generate_checkpoints(&heights);
+    let mut pickups = generate_pickups(&heights, difficulty, rng);
+    if let Some(from) = boss_arena {
+        clear_boss_arena(&mut tiles, &heights, from);
+        enemies.retain(|(x, _)| *x < from);
+        pickups.retain(|(x, _, _)| *x < from);
+        add_arena_checkpoint(&mut checkpoints, &heights, from);
+    }
+    Level {
+        name: format!("Seed {}", seed),
+        par_time: width as f32 * 0.4,
+        tiles,
+        enemies,
+        checkpoints,
+        pickups,
+        player_start: (0., 2.),
+        winning_zone: width as f32,
+        boss_arena,
+    }
+}
+
+/// Levels the ground at the end of the level to that of the column before, returning the first
+/// column of the arena.
+fn flatten_boss_arena(heights: &mut Vec<usize>) -> usize {
+    let from = heights.len() - ARENA_WIDTH;
+    let ground = heights[from - 1].max(1);
+    heights[from - 2..].iter_mut().for_each(|height| *height = ground);
+    from
 }
 
-fn add_enemies(commands: &mut Commands, world: &Vec<usize>, materials: &Res<Materials>) {
-    world.iter().enumerate().for_each(|(x, height)| {
-        if should_add_enemy(x) {
-            insert_monster_at(commands, x, *height + 1, materials)
+/// Replaces the checkpoints close to the arena with one right before it, where dying to the boss
+/// brings the player back.
+fn add_arena_checkpoint(checkpoints: &mut Vec<(usize, usize)>, heights: &Vec<usize>, from: usize) {
+    checkpoints.retain(|(x, _)| *x < from - 2);
+    checkpoints.push((from - 2, heights[from - 2] + 1));
+}
+
+/// Removes the platforms and overhangs reaching into the arena.
+fn clear_boss_arena(tiles: &mut TileGrid, heights: &Vec<usize>, from: usize) {
+    for x in from..heights.len() {
+        for y in heights[x] + 1..tiles.height() {
+            tiles.set(x, y, Tile::Empty);
         }
+    }
+}
+
+/// Turns the level back into plain ground: pits are filled, floating tiles removed and steps
+/// lowered to one tile, which the player can always jump.
+fn repair_level(level: &mut Level) {
+    let tiles = &level.tiles;
+    let mut heights: Vec<usize> = (0..tiles.width())
+        .map(|x| (1..tiles.height()).take_while(|y| !tiles.is_empty(x, *y)).count())
+        .collect();
+    let mut previous = 1;
+    heights.iter_mut().for_each(|height| {
+        *height = (*height).clamp(1, previous + 1);
+        previous = *height;
+    });
+    level.tiles = TileGrid::from_heights(&heights, 6);
+    level.enemies = level
+        .enemies
+        .iter()
+        .filter(|(x, _)| *x < heights.len())
+        .map(|(x, _)| (*x, heights[*x] + 1))
+        .collect();
+    level.pickups = level
+        .pickups
+        .iter()
+        .filter(|(x, _, _)| *x < heights.len())
+        .map(|(x, _, kind)| (*x, heights[*x] + 1, *kind))
+        .collect();
+    level.checkpoints = generate_checkpoints(&heights);
+    if let Some(from) = level.boss_arena {
+        add_arena_checkpoint(&mut level.checkpoints, &heights, from);
+    }
+}
+
+fn add_sprites(commands: &mut Commands, materials: &Res<Materials>, tiles: &TileGrid) {
+    tiles.runs().iter().for_each(|run| {
+        add_tile(commands, materials, run);
+    });
+}
+
+/// Spawns a monster at each enemy position, of a kind allowed by the difficulty.
+fn add_enemies(
+    commands: &mut Commands,
+    enemies: &Vec<(usize, usize)>,
+    monster_table: &MonsterTable,
+    difficulty: &Difficulty,
+    rng: &mut StdRng,
+) {
+    enemies.iter().for_each(|(x, y)| {
+        let definition = monster_table.choose(difficulty.monster_level, rng);
+        insert_monster_at(commands, *x as f32, *y as f32, &definition)
     })
 }
 
-fn should_add_enemy(x: usize) -> bool {
+fn generate_enemies(
+    world: &Vec<usize>,
+    difficulty: &Difficulty,
+    rng: &mut StdRng,
+) -> Vec<(usize, usize)> {
+    world
+        .iter()
+        .enumerate()
+        .filter(|(x, height)| **height > 0 && should_add_enemy(*x, difficulty, rng))
+        .map(|(x, height)| (x, *height + 1))
+        .collect()
+}
+
+fn add_checkpoints(
+    commands: &mut Commands,
+    checkpoints: &Vec<(usize, usize)>,
+    materials: &Res<Materials>,
+) {
+    checkpoints
+        .iter()
+        .for_each(|(x, y)| insert_checkpoint_at(commands, *x, *y, materials))
+}
+
+fn add_pickups(
+    commands: &mut Commands,
+    pickups: &Vec<(usize, usize, PickupKind)>,
+    materials: &Res<Materials>,
+) {
+    pickups
+        .iter()
+        .for_each(|(x, y, kind)| insert_pickup_at(commands, *x as f32, *y as f32, *kind, materials))
+}
+
+/// Scatters pickups on the ground, away from the start of the level.
+fn generate_pickups(
+    heights: &Vec<usize>,
+    difficulty: &Difficulty,
+    rng: &mut StdRng,
+) -> Vec<(usize, usize, PickupKind)> {
+    (8..heights.len())
+        .filter_map(|x| {
+            if heights[x] > 0 && rng.gen_range(0..100) < difficulty.pickup_chance {
+                Some((x, heights[x] + 1, random_pickup_kind(rng)))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn random_pickup_kind(rng: &mut StdRng) -> PickupKind {
+    match rng.gen_range(0..100) {
+        n if n < 30 => PickupKind::Ammo,
+        n if n < 50 => PickupKind::Health,
+        n if n < 70 => PickupKind::Weapon(
+            *[WeaponKind::Shotgun, WeaponKind::RapidFire, WeaponKind::Bouncing]
+                .choose(rng)
+                .unwrap(),
+        ),
+        n if n < 80 => PickupKind::SpeedBoost,
+        n if n < 90 => PickupKind::JumpBoost,
+        _ => PickupKind::Shield,
+    }
+}
+
+const CHECKPOINT_SPACING: usize = 40;
+
+/// Places a checkpoint on solid ground every `CHECKPOINT_SPACING` columns.
+fn generate_checkpoints(heights: &Vec<usize>) -> Vec<(usize, usize)> {
+    (1..heights.len() / CHECKPOINT_SPACING)
+        .filter_map(|index| {
+            (index * CHECKPOINT_SPACING..heights.len())
+                .find(|x| heights[*x] > 0)
+                .map(|x| (x, heights[x] + 1))
+        })
+        .collect()
+}
+
+fn should_add_enemy(x: usize, difficulty: &Difficulty, rng: &mut StdRng) -> bool {
     if x <= 5 {
         return false;
     }
-    let mut rng = thread_rng();
     let random_number: u32 = rng.gen_range(0..100);
-    match random_number {
-        0..=90 => false,
-        _ => true,
-    }
+    random_number >= 100 - difficulty.enemy_chance
 }
 
-fn create_world(width: usize) -> Vec<usize> {
+fn create_world(width: usize, difficulty: &Difficulty, rng: &mut StdRng) -> Vec<usize> {
     let mut heights: Vec<usize> = Vec::with_capacity(width);
     let mut height = 1;
     (0..width).for_each(|_| {
         heights.push(height);
-        height = get_next_height(height)
+        height = get_next_height(height, difficulty, rng)
     });
     heights
 }
 
-fn get_next_height(current_height: usize) -> usize {
-    let next_height = current_height as isize + get_random_height_delta();
+fn get_next_height(current_height: usize, difficulty: &Difficulty, rng: &mut StdRng) -> usize {
+    let next_height = current_height as isize + get_random_height_delta(difficulty, rng);
     return if next_height > 0 {
         next_height as usize
     } else {
@@ -57,74 +357,175 @@ fn get_next_height(current_height: usize) -> usize {
     };
 }
 
-fn get_random_height_delta() -> isize {
-    let mut rng = thread_rng();
+fn get_random_height_delta(difficulty: &Difficulty, rng: &mut StdRng) -> isize {
     let random_number: u32 = rng.gen_range(0..100);
+    let changing = 100 - difficulty.flat_chance;
     let delta = match random_number {
-        0..=75 => 0,
-        76..=81 => -1,
-        82..=95 => 1,
+        n if n < difficulty.flat_chance => 0,
+        n if n < difficulty.flat_chance + changing / 4 => -1,
+        n if n < 100 - changing / 6 => 1,
         _ => 2,
     };
     delta
 }
 
-fn add_tile(commands: &mut Commands, materials: &Res<Materials>, x: f32, height: usize) {
+/// Digs gaps in the terrain, keeping the start and the end of the level safe. The far side of a
+/// pit is never higher than the side the player jumps from.
+fn add_pits(heights: &mut Vec<usize>, difficulty: &Difficulty, rng: &mut StdRng) {
+    let mut x = 10;
+    while x + 10 < heights.len() {
+        let width = rng.gen_range(2..=difficulty.max_pit_width);
+        let lands_lower = heights[x + width] <= heights[x - 1];
+        if lands_lower && rng.gen_range(0..100) < difficulty.pit_chance {
+            (x..x + width).for_each(|pit| heights[pit] = 0);
+            x += width + 5;
+        } else {
+            x += 1;
+        }
+    }
+}
+
+/// Places one-way platforms floating three tiles above the surrounding ground, which is low
+/// enough to be reached with a jump.
+fn add_platforms(
+    tiles: &mut TileGrid,
+    heights: &Vec<usize>,
+    difficulty: &Difficulty,
+    rng: &mut StdRng,
+) {
+    let mut x = 8;
+    while x + 8 < heights.len() {
+        if rng.gen_range(0..100) < difficulty.platform_chance {
+            let length = rng.gen_range(3..=5);
+            let ground = heights[x - 1..x + length].iter().max().copied().unwrap_or(1).max(1);
+            let y = ground + 3;
+            if (x..x + length).all(|column| tiles.is_empty(column, y)) {
+                (x..x + length).for_each(|column| tiles.set(column, y, Tile::OneWay));
+                x += length + 3;
+                continue;
+            }
+        }
+        x += 1;
+    }
+}
+
+/// Adds solid ceilings over flat ground, low enough for the player to bump into when jumping.
+fn add_overhangs(
+    tiles: &mut TileGrid,
+    heights: &Vec<usize>,
+    difficulty: &Difficulty,
+    rng: &mut StdRng,
+) {
+    let mut x = 8;
+    while x + 8 < heights.len() {
+        let length = rng.gen_range(2..=4);
+        let ground = heights[x];
+        let is_flat = ground > 0 && heights[x..x + length].iter().all(|h| *h == ground);
+        if is_flat && rng.gen_range(0..100) < difficulty.overhang_chance {
+            let y = ground + 4;
+            let is_free = (x - 1..=x + length)
+                .all(|column| (y - 1..=y + 1).all(|row| tiles.is_empty(column, row)));
+            if is_free {
+                (x..x + length).for_each(|column| tiles.set(column, y, Tile::Solid));
+                x += length + 3;
+                continue;
+            }
+        }
+        x += 1;
+    }
+}
+
+fn add_tile(commands: &mut Commands, materials: &Res<Materials>, run: &TileRun) {
+    let width = (run.to - run.from) as f32;
+    let color = match run.tile {
+        Tile::OneWay => materials.platform_material.clone(),
+        _ => materials.floor_material.clone(),
+    };
     commands.spawn_bundle(SpriteBundle {
         sprite: Sprite {
-            color: materials.floor_material.clone(),
-            custom_size: Vec2::new(1., height as f32).into(),
+            color,
+            custom_size: Vec2::new(width, 1.).into(),
             ..Default::default()
         },
-        transform: Transform::from_translation(Vec3::new(x, height as f32 / 2. + 0.5, 0.)),
+        transform: Transform::from_translation(Vec3::new(
+            run.from as f32 + width / 2. - 0.5,
+            run.y as f32,
+            0.,
+        )),
         ..Default::default()
     });
 }
 
-fn add_colliders(world: &Vec<usize>, commands: &mut Commands) {
-    let max = match world.iter().max() {
-        Some(m) => m,
-        _ => panic!("add_colliders: World is empty"),
-    };
-    (1..=*max).for_each(|floor_height| {
-        let mut start: Option<usize> = None;
-        world
-            .iter()
-            .enumerate()
-            .for_each(|(index, height_at_index)| {
-                if *height_at_index >= floor_height && start.is_none() {
-                    start = Some(index);
-                } else if *height_at_index < floor_height && start.is_some() {
-                    add_collider(commands, floor_height, *start.get_or_insert(0), index);
-                    start = None
-                }
-            });
-
-        if start.is_some() {
-            add_collider(commands, floor_height, *start.get_or_insert(0), world.len());
-        }
-    })
+fn add_colliders(tiles: &TileGrid, commands: &mut Commands) {
+    if tiles.width() == 0 {
+        panic!("add_colliders: World is empty");
+    }
+    tiles
+        .runs()
+        .iter()
+        .for_each(|run| add_collider(commands, run));
 }
 
-fn add_collider(commands: &mut Commands, height: usize, from: usize, to: usize) {
-    let width = to - from;
+fn add_collider(commands: &mut Commands, run: &TileRun) {
+    let width = run.to - run.from;
     let half_width = width as f32 / 2.;
     let rigid_body = RigidBodyBundle {
-        position: Vec2::new(from as f32 + half_width - 0.5, height as f32).into(),
+        position: Vec2::new(run.from as f32 + half_width - 0.5, run.y as f32).into(),
         body_type: RigidBodyType::Static.into(),
         ..Default::default()
     };
+    let active_hooks = match run.tile {
+        Tile::OneWay => ActiveHooks::MODIFY_SOLVER_CONTACTS,
+        _ => ActiveHooks::empty(),
+    };
     let collider = ColliderBundle {
         shape: ColliderShape::cuboid(half_width, 0.5).into(),
+        flags: ColliderFlags {
+            active_hooks,
+            ..Default::default()
+        }
+        .into(),
         ..Default::default()
     };
-    commands
-        .spawn_bundle(rigid_body)
+    let mut entity = commands.spawn_bundle(rigid_body);
+    entity
         .insert_bundle(collider)
         .insert(RigidBodyPositionSync::Discrete);
+    if run.tile == Tile::OneWay {
+        entity.insert(OneWayPlatform);
+    }
+}
+
+/// Lets bodies pass through one-way platforms unless they land on them from above.
+pub struct OneWayPlatformHook;
+
+impl<'a> PhysicsHooksWithQuery<NoUserData<'a>> for OneWayPlatformHook {
+    fn modify_solver_contacts(
+        &self,
+        context: &mut ContactModificationContext<RigidBodyComponentsSet, ColliderComponentsSet>,
+        _user_data: &Query<NoUserData<'a>>,
+    ) {
+        let is_platform = |handle: ColliderHandle| {
+            let flags: Option<&ColliderFlags> = context.colliders.get(handle.0);
+            flags.map_or(false, |flags| {
+                flags
+                    .active_hooks
+                    .contains(ActiveHooks::MODIFY_SOLVER_CONTACTS)
+            })
+        };
+        let allowed_normal = if is_platform(context.collider1) {
+            Vector::y()
+        } else if is_platform(context.collider2) {
+            -Vector::y()
+        } else {
+            return;
+        };
+        context.update_as_oneway_platform(&allowed_normal, std::f32::consts::FRAC_PI_4);
+    }
 }
 
-fn add_winning_zone(commands: &mut Commands, materials: &Res<Materials>, x: f32) {
+/// Adds the winning zone, locked until the boss is defeated on boss levels.
+fn add_winning_zone(commands: &mut Commands, materials: &Res<Materials>, x: f32, locked: bool) {
     let height = 800.;
     let rigid_body = RigidBodyBundle {
         position: Vec2::new(x, 0.).into(),
@@ -137,17 +538,24 @@ fn add_winning_zone(commands: &mut Commands, materials: &Res<Materials>, x: f32)
         ..Default::default()
     };
 
-    commands
-        .spawn_bundle(SpriteBundle {
-            sprite: Sprite {
-                color: materials.winning_zone_material.clone(),
-                custom_size: Vec2::new(1., height).into(),
-                ..Default::default()
+    let mut entity = commands.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            color: if locked {
+                materials.gate_material.clone()
+            } else {
+                materials.winning_zone_material.clone()
             },
+            custom_size: Vec2::new(1., height).into(),
             ..Default::default()
-        })
+        },
+        ..Default::default()
+    });
+    entity
         .insert_bundle(rigid_body)
         .insert_bundle(collider)
         .insert(RigidBodyPositionSync::Discrete)
         .insert(WinningZone);
+    if locked {
+        entity.insert(Locked);
+    }
 }
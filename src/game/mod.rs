@@ -6,16 +6,47 @@ mod player;
 pub use player::*;
 mod map;
 pub use map::*;
+mod level;
+pub use level::*;
+mod ron_assets;
+pub use ron_assets::*;
+mod progress;
+pub use progress::*;
+mod tiles;
+pub use tiles::*;
+mod validation;
+pub use validation::*;
+mod checkpoints;
+pub use checkpoints::*;
+mod ground;
+pub use ground::*;
+mod jumping;
+pub use jumping::*;
 mod monsters;
 pub use monsters::*;
 mod bullets;
 pub use bullets::*;
+mod weapons;
+pub use weapons::*;
+mod pickups;
+pub use pickups::*;
+mod boss;
+pub use boss::*;
 mod living_beings;
 pub use living_beings::*;
 mod monster_ai;
 pub use monster_ai::*;
+mod hud;
+pub use hud::*;
+mod score;
+pub use score::*;
+mod high_scores;
+pub use high_scores::*;
+mod save;
+pub use save::*;
 
 use super::AppState;
+use crate::actions::GameAction;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
@@ -23,15 +54,68 @@ pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(AppState::InGame).with_system(spawn_floor.system()))
+        app.insert_resource(LevelSeed::from_command_line())
+            .init_resource::<LevelProgress>()
+            .init_resource::<LevelTime>()
+            .init_resource::<Score>()
+            .insert_resource(HighScores::load())
+            .add_event::<ScoreEvent>()
+            .add_event::<SaveRunEvent>()
+            .add_event::<ContinueRunEvent>()
+            .add_asset::<Level>()
+            .init_asset_loader::<RonAssetLoader<Level>>()
+            .add_startup_system(load_level_file.system())
+            .add_asset::<MonsterTable>()
+            .init_asset_loader::<RonAssetLoader<MonsterTable>>()
+            .add_startup_system(load_monster_table.system())
+            .add_system_set(
+                SystemSet::on_enter(AppState::MainMenu)
+                    .with_system(reroll_level_seed.system())
+                    .with_system(restore_level_file.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame)
+                    .with_system(spawn_floor.system())
+                    .with_system(reset_level_time.system())
+                    .with_system(reset_score.system()),
+            )
             .add_system_set(
                 SystemSet::on_update(AppState::InGame)
-                    .with_system(back_to_main_menu_controls.system()),
+                    .with_system(pause_controls.system())
+                    .with_system(tick_level_time.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame).with_system(resume_physics.system()),
+            )
+            .add_system_set(
+                SystemSet::on_pause(AppState::InGame).with_system(pause_physics.system()),
+            )
+            .add_system_set(
+                SystemSet::on_resume(AppState::InGame).with_system(resume_physics.system()),
             )
+            .insert_resource(PhysicsHooksWithQueryObject::<NoUserData>(Box::new(
+                OneWayPlatformHook,
+            )))
             .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
             .add_plugin(PlayerPlugin)
             .add_plugin(MonsterAiPlugin)
+            .add_plugin(HudPlugin)
+            .add_plugin(BossPlugin)
             .add_system(on_level_success.system())
+            // Not tied to InGame, so that points sent when leaving the level still count.
+            .add_system(on_score_event.system())
+            .add_system_set(SystemSet::on_update(AppState::Paused).with_system(save_run.system()))
+            .add_system_set(
+                SystemSet::on_update(AppState::MainMenu)
+                    .with_system(continue_run.system())
+                    .with_system(resume_pending_run.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::MainMenu).with_system(cancel_pending_run.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::GameOver).with_system(delete_saved_run.system()),
+            )
             .add_startup_system(setup.system());
     }
 }
@@ -40,28 +124,54 @@ fn setup(mut commands: Commands) {
     commands.insert_resource(Materials {
         player_material: Color::rgb(0.969, 0.769, 0.784).into(),
         floor_material: Color::rgb(0.7, 0.7, 0.7).into(),
-        monster_material: Color::rgb(0.8, 0., 0.).into(),
+        platform_material: Color::rgb(0.55, 0.45, 0.35).into(),
         bullet_material: Color::rgb(0.8, 0.8, 0.).into(),
+        monster_bullet_material: Color::rgb(0.5, 1., 0.3).into(),
         winning_zone_material: Color::rgb(0., 0.75, 1.).into(),
+        boss_material: Color::rgb(0.5, 0., 0.3).into(),
+        boss_enraged_material: Color::rgb(0.9, 0.1, 0.3).into(),
+        gate_material: Color::rgb(0.35, 0.3, 0.3).into(),
+        boss_health_material: Color::rgb(0.85, 0.1, 0.2).into(),
+        checkpoint_material: Color::rgb(0.5, 0.5, 0.5).into(),
+        checkpoint_active_material: Color::rgb(0.2, 0.9, 0.2).into(),
+        hud_background_material: Color::rgba(0., 0., 0., 0.5).into(),
+        progress_background_material: Color::rgb(0.25, 0.25, 0.25).into(),
+        progress_material: Color::rgb(0., 0.75, 1.).into(),
+        ammo_pickup_material: Color::rgb(0.8, 0.8, 0.).into(),
+        health_pickup_material: Color::rgb(0.9, 0.3, 0.4).into(),
+        weapon_pickup_material: Color::rgb(1., 0.55, 0.).into(),
+        boost_pickup_material: Color::rgb(0.3, 0.9, 0.5).into(),
+        shield_pickup_material: Color::rgb(0.4, 0.6, 1.).into(),
     });
 }
 
-fn back_to_main_menu_controls(
-    mut keys: ResMut<Input<KeyCode>>,
+fn pause_controls(
+    mut actions: ResMut<Input<GameAction>>,
     mut app_state: ResMut<State<AppState>>,
 ) {
     if *app_state.current() == AppState::InGame {
-        if keys.just_pressed(KeyCode::Escape) {
-            app_state.set(AppState::MainMenu).unwrap();
-            keys.reset(KeyCode::Escape);
+        if actions.clear_just_pressed(GameAction::Pause) {
+            // Pausing is ignored when another transition is already queued on this frame.
+            let _ = app_state.push(AppState::Paused);
         }
     }
 }
 
+fn pause_physics(mut rapier_config: ResMut<RapierConfiguration>) {
+    rapier_config.physics_pipeline_active = false;
+}
+
+fn resume_physics(mut rapier_config: ResMut<RapierConfiguration>) {
+    rapier_config.physics_pipeline_active = true;
+}
+
 fn on_level_success(
     mut app_state: ResMut<State<AppState>>,
+    level_time: Res<LevelTime>,
+    score: Res<Score>,
+    mut send_score: EventWriter<ScoreEvent>,
     players: Query<Entity, With<Player>>,
-    winning_zones: Query<Entity, With<WinningZone>>,
+    winning_zones: Query<Entity, (With<WinningZone>, Without<Locked>)>,
     mut contact_events: EventReader<ContactEvent>,
 ) {
     for contact_event in contact_events.iter() {
@@ -73,7 +183,20 @@ fn on_level_success(
                 let w = winning_zone.unwrap();
                 if (h1.entity() == p && h2.entity() == w) || (h1.entity() == w && h2.entity() == p)
                 {
-                    app_state.set(AppState::BetweenLevels).unwrap();
+                    // The player may have lost their last life on this frame.
+                    if app_state.set(AppState::BetweenLevels).is_err() {
+                        return;
+                    }
+                    send_score.send(ScoreEvent::LevelCompleted);
+                    if level_time.elapsed < level_time.par {
+                        send_score.send(ScoreEvent::TimeBonus {
+                            seconds_under_par: (level_time.par - level_time.elapsed) as u32,
+                        });
+                    }
+                    if !score.damage_taken {
+                        send_score.send(ScoreEvent::NoDamageBonus);
+                    }
+                    return;
                 }
             }
         }
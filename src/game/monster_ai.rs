@@ -1,81 +1,368 @@
-use bevy::core::FixedTimestep;
+use bevy::ecs::schedule::ShouldRun;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 use rand::{thread_rng, Rng};
 
 use super::super::AppState;
-use super::{GameDirection, Jumper, Monster};
+use super::{
+    Bullet, BulletFiredEvent, Faction, GameDirection, Health, Jumper, LivingBeing,
+    LivingBeingHitEvent, Monster, MonsterBehaviour, Player, Weapon,
+};
 
-struct MonsterWalkedIntoWallEvent {
-    entity: Entity,
+const MONSTER_JUMP_INTERVAL: f32 = 2.;
+/// Seconds between the starts of two charges, and how long a charge lasts.
+const CHARGE_INTERVAL: f64 = 3.;
+const CHARGE_DURATION: f64 = 1.;
+const CHARGE_SPEED_FACTOR: f32 = 2.5;
+/// Vertical speed and frequency of the flyers' bobbing.
+const FLYER_BOB_SPEED: f32 = 1.;
+const FLYER_BOB_FREQUENCY: f32 = 2.;
+
+/// Seconds a monster stands still after turning around.
+const IDLE_DURATION: f32 = 1.;
+/// Seconds a hit monster stays at the mercy of the knockback.
+const STUN_DURATION: f32 = 0.4;
+const FLEE_DURATION: f32 = 3.;
+const FLEE_SPEED_FACTOR: f32 = 1.5;
+const CHASE_SPEED_FACTOR: f32 = 1.5;
+/// How much higher than a chasing monster the player must be for it to jump after them.
+const CHASE_JUMP_HEIGHT: f32 = 1.;
+/// Vertical speed of flyers closing in on the player.
+const FLYER_CHASE_SPEED: f32 = 2.;
+/// How far ahead of its side a monster looks for walls.
+const WALL_DISTANCE: f32 = 0.2;
+/// How far ahead of its side a monster looks for the ground, and how deep.
+const LEDGE_LOOKAHEAD: f32 = 0.3;
+const LEDGE_DEPTH: f32 = 0.6;
+
+/// What a monster is currently doing.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MonsterState {
+    /// Walks around, turning at walls and ledges.
+    Patrol,
+    /// Stands still for a moment after turning around.
+    Idle,
+    /// Runs towards where the player was last seen, jumping over walls and stopping at ledges.
+    Chase,
+    /// Runs away from the player after being badly hurt.
+    Flee,
+    /// Pushed back by a hit, not moving on its own.
+    Stunned,
+}
+
+#[derive(Component)]
+pub struct MonsterAi {
+    pub state: MonsterState,
+    /// Seconds left in a timed state.
+    pub timer: f32,
+    /// Set when a ledge stops a chase.
+    pub blocked: bool,
+    /// Distance within which the player is noticed.
+    pub aggro_radius: f32,
+    /// Seconds a chase goes on without seeing the player.
+    pub give_up_time: f32,
+    /// Where the player was last seen during a chase.
+    pub target: Option<Vec2>,
+    /// Seconds since the player was last seen.
+    pub time_unseen: f32,
+}
+
+impl MonsterAi {
+    pub fn new(aggro_radius: f32, give_up_time: f32) -> Self {
+        MonsterAi {
+            state: MonsterState::Patrol,
+            timer: 0.,
+            blocked: false,
+            aggro_radius,
+            give_up_time,
+            target: None,
+            time_unseen: 0.,
+        }
+    }
+
+    pub fn enter(&mut self, state: MonsterState, duration: f32) {
+        self.state = state;
+        self.timer = duration;
+        self.blocked = false;
+    }
 }
 
 pub struct MonsterAiPlugin;
 
 impl Plugin for MonsterAiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<MonsterWalkedIntoWallEvent>()
-            .add_system_set(
-                SystemSet::on_update(AppState::InGame)
-                    .with_system(monster_walking_system.system())
-                    .with_system(monster_wall_contact_detection.system())
-                    .with_system(monster_change_direction_on_contact.system()),
-            )
-            .add_system_set(
-                SystemSet::new()
-                    .with_run_criteria(FixedTimestep::step(2.0))
-                    .with_system(monster_jumps.system()),
-            );
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(monster_senses.system())
+                .with_system(monster_hit_reactions.system())
+                .with_system(monster_walking_system.system())
+                .with_system(monster_fire.system()),
+        )
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(monster_jump_step.system())
+                .with_system(monster_jumps.system()),
+        );
     }
 }
 
-fn monster_walking_system(mut monsters: Query<(&Monster, &mut RigidBodyVelocityComponent)>) {
-    for (monster, mut velocity) in monsters.iter_mut() {
-        let speed = match monster.facing_direction {
-            GameDirection::Left => -monster.speed,
-            GameDirection::Right => monster.speed,
-        };
+/// A fixed timestep only counting time spent in game, so that monsters freeze while paused.
+fn monster_jump_step(
+    time: Res<Time>,
+    state: Res<State<AppState>>,
+    mut elapsed: Local<f32>,
+) -> ShouldRun {
+    if *state.current() != AppState::InGame {
+        return ShouldRun::No;
+    }
+    *elapsed += time.delta_seconds();
+    if *elapsed >= MONSTER_JUMP_INTERVAL {
+        *elapsed -= MONSTER_JUMP_INTERVAL;
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+fn direction_sign(direction: GameDirection) -> f32 {
+    match direction {
+        GameDirection::Left => -1.,
+        GameDirection::Right => 1.,
+    }
+}
 
-        velocity.linvel = Vec2::new(speed, velocity.linvel.y).into();
+fn opposite(direction: GameDirection) -> GameDirection {
+    match direction {
+        GameDirection::Left => GameDirection::Right,
+        GameDirection::Right => GameDirection::Left,
     }
 }
 
-fn monster_wall_contact_detection(
-    monsters: Query<Entity, With<Monster>>,
-    mut contact_events: EventReader<ContactEvent>,
-    mut send_monster_walked_into_wall: EventWriter<MonsterWalkedIntoWallEvent>,
+/// Moves monsters from one state to the next. Ray casts against the terrain tell whether the
+/// player is in sight, and whether walls or ledges are ahead.
+fn monster_senses(
+    time: Res<Time>,
+    query_pipeline: Res<QueryPipeline>,
+    collider_query: QueryPipelineColliderComponentsQuery,
+    collider_types: Query<&ColliderTypeComponent>,
+    not_terrain: Query<(), Or<(With<LivingBeing>, With<Bullet>)>>,
+    players: Query<&RigidBodyPositionComponent, With<Player>>,
+    mut monsters: Query<(
+        Entity,
+        &mut Monster,
+        &mut MonsterAi,
+        &Health,
+        &RigidBodyPositionComponent,
+        &ColliderShapeComponent,
+        Option<&mut Jumper>,
+    )>,
 ) {
-    for contact_event in contact_events.iter() {
-        if let ContactEvent::Started(h1, h2) = contact_event {
-            for monster in monsters.iter() {
-                if h1.entity() == monster || h2.entity() == monster {
-                    send_monster_walked_into_wall
-                        .send(MonsterWalkedIntoWallEvent { entity: monster })
+    let colliders = QueryPipelineColliderComponentsSet(&collider_query);
+    let player = players.get_single().ok().map(|position| {
+        let translation = position.position.translation;
+        Vec2::new(translation.x, translation.y)
+    });
+    for (entity, mut monster, mut ai, health, position, shape, jumper) in monsters.iter_mut() {
+        let translation = position.position.translation;
+        let center = Vec2::new(translation.x, translation.y);
+        let half_extents = shape.compute_local_aabb().half_extents();
+
+        ai.timer -= time.delta_seconds();
+        match ai.state {
+            MonsterState::Stunned if ai.timer <= 0. => {
+                if health.current * 2 <= health.max {
+                    if let Some(player) = player {
+                        monster.facing_direction = if player.x < center.x {
+                            GameDirection::Right
+                        } else {
+                            GameDirection::Left
+                        };
+                    }
+                    ai.enter(MonsterState::Flee, FLEE_DURATION);
+                } else {
+                    ai.enter(MonsterState::Patrol, 0.);
                 }
             }
+            MonsterState::Idle | MonsterState::Flee if ai.timer <= 0. => {
+                ai.enter(MonsterState::Patrol, 0.)
+            }
+            MonsterState::Stunned => continue,
+            _ => (),
+        }
+
+        let is_terrain = |handle: ColliderHandle| {
+            let other = handle.entity();
+            other != entity
+                && not_terrain.get(other).is_err()
+                && collider_types
+                    .get(other)
+                    .map_or(false, |collider_type| **collider_type == ColliderType::Solid)
+        };
+        let cast = |origin: Vec2, direction: Vec2, length: f32| {
+            let ray = Ray::new(
+                Point::new(origin.x, origin.y),
+                Vector::new(direction.x, direction.y),
+            );
+            query_pipeline
+                .cast_ray(
+                    &colliders,
+                    &ray,
+                    length,
+                    true,
+                    InteractionGroups::all(),
+                    Some(&is_terrain),
+                )
+                .is_some()
+        };
+
+        let seen_player = player.filter(|player| {
+            let distance = player.distance(center);
+            distance <= ai.aggro_radius
+                && !cast(center, (*player - center).normalize_or_zero(), distance)
+        });
+        match seen_player {
+            Some(player) => {
+                if matches!(ai.state, MonsterState::Patrol | MonsterState::Idle) {
+                    ai.enter(MonsterState::Chase, 0.);
+                }
+                ai.target = Some(player);
+                ai.time_unseen = 0.;
+            }
+            None => {
+                ai.time_unseen += time.delta_seconds();
+                if ai.state == MonsterState::Chase && ai.time_unseen > ai.give_up_time {
+                    ai.target = None;
+                    ai.enter(MonsterState::Patrol, 0.);
+                }
+            }
+        }
+        if let (MonsterState::Chase, Some(target)) = (ai.state, ai.target) {
+            monster.facing_direction = if target.x < center.x {
+                GameDirection::Left
+            } else {
+                GameDirection::Right
+            };
+        }
+        if ai.state == MonsterState::Idle {
+            continue;
+        }
+
+        let sign = direction_sign(monster.facing_direction);
+        let wall_ahead = cast(center, Vec2::new(sign, 0.), half_extents.x + WALL_DISTANCE);
+        // Only walking monsters standing on the ground care about ledges.
+        let ledge_ahead = monster.behaviour != MonsterBehaviour::Flyer
+            && cast(center, Vec2::new(0., -1.), half_extents.y + 0.2)
+            && !cast(
+                center + Vec2::new(sign * (half_extents.x + LEDGE_LOOKAHEAD), 0.),
+                Vec2::new(0., -1.),
+                half_extents.y + LEDGE_DEPTH,
+            );
+
+        let blocked = wall_ahead || ledge_ahead;
+        match ai.state {
+            MonsterState::Patrol if blocked => {
+                monster.facing_direction = opposite(monster.facing_direction);
+                ai.enter(MonsterState::Idle, IDLE_DURATION);
+            }
+            MonsterState::Flee if blocked => {
+                monster.facing_direction = opposite(monster.facing_direction);
+            }
+            MonsterState::Chase => {
+                ai.blocked = ledge_ahead;
+                let target_above = ai
+                    .target
+                    .map_or(false, |target| target.y > center.y + CHASE_JUMP_HEIGHT);
+                if let Some(mut jumper) = jumper {
+                    if wall_ahead || target_above {
+                        jumper.request_jump();
+                    }
+                }
+            }
+            _ => (),
         }
     }
 }
 
-fn monster_change_direction_on_contact(
-    mut events: EventReader<MonsterWalkedIntoWallEvent>,
-    mut monster_query: Query<&mut Monster>,
+fn monster_hit_reactions(
+    mut living_being_hit_events: EventReader<LivingBeingHitEvent>,
+    mut monsters: Query<&mut MonsterAi>,
 ) {
-    for event in events.iter() {
-        // bullet contacts may destroy monster before running this system.
-        if let Ok(mut monster) = monster_query.get_mut(event.entity) {
-            monster.facing_direction = match monster.facing_direction {
-                GameDirection::Left => GameDirection::Right,
-                GameDirection::Right => GameDirection::Left,
+    for event in living_being_hit_events.iter() {
+        if let Ok(mut ai) = monsters.get_mut(event.entity) {
+            ai.enter(MonsterState::Stunned, STUN_DURATION);
+        }
+    }
+}
+
+fn monster_walking_system(
+    time: Res<Time>,
+    mut monsters: Query<(
+        &Monster,
+        &MonsterAi,
+        &RigidBodyPositionComponent,
+        &mut RigidBodyVelocityComponent,
+    )>,
+) {
+    let charging = time.seconds_since_startup() % CHARGE_INTERVAL < CHARGE_DURATION;
+    let bob = (time.seconds_since_startup() as f32 * FLYER_BOB_FREQUENCY).sin() * FLYER_BOB_SPEED;
+    for (monster, ai, position, mut velocity) in monsters.iter_mut() {
+        let is_charger = monster.behaviour == MonsterBehaviour::Charger;
+        let factor = match ai.state {
+            // The knockback moves stunned monsters.
+            MonsterState::Stunned => continue,
+            MonsterState::Idle => 0.,
+            MonsterState::Chase if ai.blocked => 0.,
+            MonsterState::Chase if monster.behaviour == MonsterBehaviour::Shooter => 0.,
+            MonsterState::Chase if is_charger => CHARGE_SPEED_FACTOR,
+            MonsterState::Chase => CHASE_SPEED_FACTOR,
+            MonsterState::Flee => FLEE_SPEED_FACTOR,
+            MonsterState::Patrol if is_charger && charging => CHARGE_SPEED_FACTOR,
+            MonsterState::Patrol => 1.,
+        };
+        let speed = direction_sign(monster.facing_direction) * monster.speed * factor;
+        let vertical = match (monster.behaviour, ai.state, ai.target) {
+            (MonsterBehaviour::Flyer, MonsterState::Chase, Some(target)) => {
+                let height = target.y - position.position.translation.y;
+                height.clamp(-1., 1.) * FLYER_CHASE_SPEED
             }
+            (MonsterBehaviour::Flyer, _, _) => bob,
+            _ => velocity.linvel.y,
+        };
+
+        velocity.linvel = Vec2::new(speed, vertical).into();
+    }
+}
+
+/// Fires the weapons of monsters at the player they have in sight.
+fn monster_fire(
+    mut send_fire_event: EventWriter<BulletFiredEvent>,
+    mut monsters: Query<(&MonsterAi, &RigidBodyPositionComponent, &mut Weapon), With<Monster>>,
+) {
+    for (ai, position, mut weapon) in monsters.iter_mut() {
+        let target = match ai.target {
+            Some(target) if ai.state == MonsterState::Chase && ai.time_unseen == 0. => target,
+            _ => continue,
+        };
+        let translation = position.position.translation;
+        let center = Vec2::new(translation.x, translation.y);
+        for direction in weapon.fire((target - center).normalize_or_zero()) {
+            send_fire_event.send(BulletFiredEvent {
+                position: center,
+                direction,
+                speed: weapon.projectile_speed,
+                damage: weapon.damage,
+                bounces: weapon.bounces,
+                faction: Faction::Monsters,
+            });
         }
     }
 }
 
-fn monster_jumps(mut monsters: Query<(&mut Jumper, &mut RigidBodyVelocityComponent), With<Monster>>) {
-    for (monster, mut velocity) in monsters.iter_mut() {
-        if should_jump() {
-            velocity.linvel = Vec2::new(0., monster.jump_impulse).into();
+fn monster_jumps(mut monsters: Query<(&Monster, &MonsterAi, &mut Jumper)>) {
+    for (monster, ai, mut jumper) in monsters.iter_mut() {
+        let moving = matches!(ai.state, MonsterState::Patrol | MonsterState::Chase);
+        if monster.behaviour == MonsterBehaviour::Jumper && moving && should_jump() {
+            jumper.request_jump();
         }
     }
 }
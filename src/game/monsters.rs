@@ -1,51 +1,189 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, reflect::TypeUuid};
 use bevy_rapier2d::prelude::*;
+use rand::prelude::*;
+use serde::Deserialize;
 
-use super::{Enemy, GameDirection, Jumper, LivingBeing, Materials, Monster};
+use super::{
+    Enemy, GameDirection, Health, Jumper, LivingBeing, Monster, MonsterAi, RonAsset, Weapon,
+    WeaponKind, MONSTER_GROUP,
+};
 
-pub fn insert_monster_at(commands: &mut Commands, x: usize, y: usize, materials: &Res<Materials>) {
+const MONSTER_TABLE_PATH: &str = "monsters/default.monsters.ron";
+
+/// How a kind of monster moves around.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+pub enum MonsterBehaviour {
+    /// Walks, turning around at walls and ledges.
+    Walker,
+    /// Walks and jumps now and then.
+    Jumper,
+    /// Flies level, ignoring gravity and pits.
+    Flyer,
+    /// Walks slowly, standing still to fire at the player it sees.
+    Shooter,
+    /// Walks with sudden bursts of speed.
+    Charger,
+}
+
+/// A kind of monster, as described in the monster table.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MonsterDefinition {
+    pub name: String,
+    pub size: (f32, f32),
+    pub speed: f32,
+    pub health: u32,
+    pub behaviour: MonsterBehaviour,
+    #[serde(default = "default_jump_impulse")]
+    pub jump_impulse: f32,
+    /// Weapon fired at the player in sight.
+    #[serde(default)]
+    pub weapon: Option<WeaponKind>,
+    /// Red, green and blue components of the sprite's colour.
+    pub color: (f32, f32, f32),
+    /// Distance within which the monster notices the player, if nothing blocks its view.
+    #[serde(default = "default_aggro_radius")]
+    pub aggro_radius: f32,
+    /// Seconds the monster keeps chasing the player after losing sight of them.
+    #[serde(default = "default_give_up_time")]
+    pub give_up_time: f32,
+    /// Index of the first level the monster appears in.
+    #[serde(default)]
+    pub min_level: usize,
+    /// How often the monster is picked relative to the others.
+    pub weight: u32,
+}
+
+fn default_jump_impulse() -> f32 {
+    14.
+}
+
+fn default_aggro_radius() -> f32 {
+    6.
+}
+
+fn default_give_up_time() -> f32 {
+    2.
+}
+
+impl Default for MonsterDefinition {
+    fn default() -> Self {
+        MonsterDefinition {
+            name: "walker".to_string(),
+            size: (0.9, 0.9),
+            speed: 3.,
+            health: 1,
+            behaviour: MonsterBehaviour::Walker,
+            jump_impulse: default_jump_impulse(),
+            weapon: None,
+            color: (0.8, 0., 0.),
+            aggro_radius: default_aggro_radius(),
+            give_up_time: default_give_up_time(),
+            min_level: 0,
+            weight: 1,
+        }
+    }
+}
+
+/// Every kind of monster, loaded from a `.monsters.ron` file.
+#[derive(Debug, Clone, Deserialize, TypeUuid)]
+#[uuid = "3c1f2a5e-0b7d-4c8e-9a61-5d2f8e4b7a10"]
+pub struct MonsterTable {
+    pub monsters: Vec<MonsterDefinition>,
+}
+
+/// Only holds the default walker, used until the monster table is loaded.
+impl Default for MonsterTable {
+    fn default() -> Self {
+        MonsterTable {
+            monsters: vec![MonsterDefinition::default()],
+        }
+    }
+}
+
+impl MonsterTable {
+    pub fn get(&self, name: &str) -> Option<&MonsterDefinition> {
+        self.monsters.iter().find(|monster| monster.name == name)
+    }
+
+    /// Picks a monster among those allowed on the given level, by weight.
+    pub fn choose(&self, level: usize, rng: &mut StdRng) -> MonsterDefinition {
+        let available: Vec<&MonsterDefinition> = self
+            .monsters
+            .iter()
+            .filter(|monster| monster.min_level <= level)
+            .collect();
+        available
+            .choose_weighted(rng, |monster| monster.weight)
+            .map(|monster| (*monster).clone())
+            .unwrap_or_default()
+    }
+}
+
+impl RonAsset for MonsterTable {
+    const EXTENSIONS: &'static [&'static str] = &["monsters.ron"];
+}
+
+pub struct MonsterTableFile(pub Handle<MonsterTable>);
+
+pub fn load_monster_table(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(MonsterTableFile(asset_server.load(MONSTER_TABLE_PATH)));
+}
+
+/// Spawns a monster standing at `y`, or flying there.
+pub fn insert_monster_at(commands: &mut Commands, x: f32, y: f32, definition: &MonsterDefinition) {
+    let (width, height) = definition.size;
+    let flying = definition.behaviour == MonsterBehaviour::Flyer;
     let rigid_body = RigidBodyBundle {
-        position: Vec2::new(x as f32, y as f32).into(),
+        // Tall monsters stand on the same ground as others.
+        position: Vec2::new(x, y + (height - 0.9) / 2.).into(),
         mass_properties: RigidBodyMassPropsFlags::ROTATION_LOCKED.into(),
         activation: RigidBodyActivation::cannot_sleep().into(),
         forces: RigidBodyForces {
-            gravity_scale: 3.,
+            gravity_scale: if flying { 0. } else { 3. },
             ..Default::default()
         }.into(),
         ..Default::default()
     };
 
     let collider = ColliderBundle {
-        shape: ColliderShape::round_cuboid(0.35, 0.35, 0.1).into(),
+        shape: ColliderShape::round_cuboid(width / 2. - 0.1, height / 2. - 0.1, 0.1).into(),
         flags: ColliderFlags {
             active_events: ActiveEvents::CONTACT_EVENTS,
+            collision_groups: InteractionGroups::new(MONSTER_GROUP, u32::MAX),
             ..Default::default()
         }.into(),
         ..Default::default()
     };
 
+    let (red, green, blue) = definition.color;
     let sprite = SpriteBundle {
         sprite: Sprite {
-            color: materials.monster_material.clone(),
-            custom_size: Vec2::new(0.9, 0.9).into(),
+            color: Color::rgb(red, green, blue),
+            custom_size: Vec2::new(width, height).into(),
             ..Default::default()
         },
         ..Default::default()
     };
 
-    commands
-        .spawn_bundle(sprite)
+    let mut monster = commands.spawn_bundle(sprite);
+    monster
         .insert_bundle(rigid_body)
         .insert_bundle(collider)
         .insert(RigidBodyPositionSync::Discrete)
         .insert(LivingBeing)
+        .insert(Health::new(definition.health, 0.2))
         .insert(Enemy)
         .insert(Monster {
-            speed: 3.,
+            name: definition.name.clone(),
+            speed: definition.speed,
             facing_direction: GameDirection::Right,
+            behaviour: definition.behaviour,
         })
-        .insert(Jumper {
-            jump_impulse: 14.,
-            is_jumping: false,
-        });
+        .insert(MonsterAi::new(definition.aggro_radius, definition.give_up_time));
+    if !flying {
+        monster.insert(Jumper::new(definition.jump_impulse, 0.05, 0.3, 1.));
+    }
+    if let Some(kind) = definition.weapon {
+        monster.insert(Weapon::new(kind));
+    }
 }
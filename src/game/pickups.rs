@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    pick_up_weapon, Health, Holster, Invulnerable, Jumper, Materials, Player, Weapon, WeaponKind,
+    PLAYER_JUMP_IMPULSE, PLAYER_SPEED,
+};
+
+/// Seconds a speed or jump boost lasts.
+const BOOST_DURATION: f32 = 8.;
+const SPEED_BOOST: f32 = 1.5;
+const JUMP_BOOST: f32 = 1.25;
+/// Seconds during which a shield absorbs hits.
+const SHIELD_DURATION: f32 = 6.;
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PickupKind {
+    /// A magazine for the weapon in hand.
+    Ammo,
+    /// Gives back one health point.
+    Health,
+    Weapon(WeaponKind),
+    SpeedBoost,
+    JumpBoost,
+    Shield,
+}
+
+#[derive(Component)]
+pub struct Pickup(pub PickupKind);
+
+pub struct PickupEvent {
+    pub player: Entity,
+    pub kind: PickupKind,
+}
+
+/// Seconds left on the player's temporary boosts.
+#[derive(Component, Default)]
+pub struct PowerUps {
+    pub speed: f32,
+    pub jump: f32,
+}
+
+pub fn insert_pickup_at(
+    commands: &mut Commands,
+    x: f32,
+    y: f32,
+    kind: PickupKind,
+    materials: &Res<Materials>,
+) {
+    let rigid_body = RigidBodyBundle {
+        position: Vec2::new(x, y).into(),
+        body_type: RigidBodyType::Static.into(),
+        ..Default::default()
+    };
+
+    let collider = ColliderBundle {
+        collider_type: ColliderType::Sensor.into(),
+        shape: ColliderShape::cuboid(0.3, 0.3).into(),
+        flags: ColliderFlags {
+            active_events: ActiveEvents::INTERSECTION_EVENTS,
+            ..Default::default()
+        }
+        .into(),
+        ..Default::default()
+    };
+
+    let color = match kind {
+        PickupKind::Ammo => materials.ammo_pickup_material,
+        PickupKind::Health => materials.health_pickup_material,
+        PickupKind::Weapon(_) => materials.weapon_pickup_material,
+        PickupKind::SpeedBoost | PickupKind::JumpBoost => materials.boost_pickup_material,
+        PickupKind::Shield => materials.shield_pickup_material,
+    };
+    let sprite = SpriteBundle {
+        sprite: Sprite {
+            color,
+            custom_size: Vec2::new(0.5, 0.5).into(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    commands
+        .spawn_bundle(sprite)
+        .insert_bundle(rigid_body)
+        .insert_bundle(collider)
+        .insert(RigidBodyPositionSync::Discrete)
+        .insert(Pickup(kind));
+}
+
+/// Removes the pickups the player walks into, sending a `PickupEvent` for each.
+pub fn collect_pickups(
+    mut commands: Commands,
+    mut send_pickup: EventWriter<PickupEvent>,
+    players: Query<Entity, With<Player>>,
+    pickups: Query<&Pickup>,
+    mut intersection_events: EventReader<IntersectionEvent>,
+) {
+    // A pickup is only despawned at the end of the stage: collect it once.
+    let mut collected: Vec<Entity> = Vec::new();
+    for event in intersection_events.iter() {
+        if !event.intersecting {
+            continue;
+        }
+        let (e1, e2) = (event.collider1.entity(), event.collider2.entity());
+        for player in players.iter() {
+            let pickup = if e1 == player {
+                e2
+            } else if e2 == player {
+                e1
+            } else {
+                continue;
+            };
+            if let Ok(Pickup(kind)) = pickups.get(pickup) {
+                if !collected.contains(&pickup) {
+                    collected.push(pickup);
+                    send_pickup.send(PickupEvent {
+                        player,
+                        kind: *kind,
+                    });
+                    commands.entity(pickup).despawn_recursive();
+                }
+            }
+        }
+    }
+}
+
+pub fn apply_pickups(
+    mut commands: Commands,
+    mut pickup_events: EventReader<PickupEvent>,
+    mut players: Query<(&mut Health, &mut Weapon, &mut Holster, &mut PowerUps), With<Player>>,
+) {
+    for event in pickup_events.iter() {
+        let (mut health, mut weapon, mut holster, mut power_ups) =
+            match players.get_mut(event.player) {
+                Ok(player) => player,
+                Err(_) => continue,
+            };
+        match event.kind {
+            PickupKind::Ammo => {
+                let rounds = weapon.magazine_size;
+                weapon.add_ammo(rounds);
+            }
+            PickupKind::Health => health.current = (health.current + 1).min(health.max),
+            PickupKind::Weapon(kind) => pick_up_weapon(&mut weapon, &mut holster, kind),
+            PickupKind::SpeedBoost => power_ups.speed = BOOST_DURATION,
+            PickupKind::JumpBoost => power_ups.jump = BOOST_DURATION,
+            PickupKind::Shield => {
+                commands
+                    .entity(event.player)
+                    .insert(Invulnerable(Timer::from_seconds(SHIELD_DURATION, false)));
+            }
+        }
+    }
+}
+
+/// Counts down the boosts, applying them to the player's speed and jump.
+pub fn tick_power_ups(
+    time: Res<Time>,
+    mut players: Query<(&mut Player, &mut Jumper, &mut PowerUps)>,
+) {
+    for (mut player, mut jumper, mut power_ups) in players.iter_mut() {
+        power_ups.speed = (power_ups.speed - time.delta_seconds()).max(0.);
+        power_ups.jump = (power_ups.jump - time.delta_seconds()).max(0.);
+        player.speed = if power_ups.speed > 0. {
+            PLAYER_SPEED * SPEED_BOOST
+        } else {
+            PLAYER_SPEED
+        };
+        jumper.jump_impulse = if power_ups.jump > 0. {
+            PLAYER_JUMP_IMPULSE * JUMP_BOOST
+        } else {
+            PLAYER_JUMP_IMPULSE
+        };
+    }
+}
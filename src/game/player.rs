@@ -1,15 +1,22 @@
 use super::super::AppState;
-use super::camera::new_camera_2d;
+use crate::actions::GameAction;
+use super::camera::{new_camera_2d, GameCamera};
 use super::components::{Jumper, Materials, Player};
 use super::{
-    death_by_height, destroy_bullet_on_contact, kill_on_contact, on_bullet_fired,
-    on_living_being_dead, on_living_being_hit, BulletFiredEvent, Enemy, GameDirection, LivingBeing,
-    LivingBeingDeathEvent, LivingBeingHitEvent,
+    activate_checkpoints, apply_jumps, apply_pickups, collect_pickups, death_by_height,
+    despawn_spent_bullets, destroy_bullet_on_contact, detect_ground, invulnerability_countdown,
+    kill_on_contact, on_bullet_fired, on_living_being_dead, on_living_being_hit, switch_weapon,
+    tick_power_ups, update_weapons, BulletFiredEvent, Enemy, Faction, GameDirection, Health,
+    Holster, LivingBeing, LivingBeingDeathEvent, LivingBeingHitEvent, PickupEvent, PowerUps,
+    Weapon, WeaponKind, PLAYER_GROUP,
 };
 use bevy::prelude::*;
-use bevy::render::camera::Camera;
 use bevy_rapier2d::prelude::*;
 
+pub const PLAYER_SPEED: f32 = 7.;
+pub const PLAYER_JUMP_IMPULSE: f32 = 14.;
+pub const PLAYER_GRAVITY_SCALE: f32 = 3.;
+
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
@@ -17,34 +24,48 @@ impl Plugin for PlayerPlugin {
         app.add_event::<LivingBeingHitEvent>()
             .add_event::<LivingBeingDeathEvent>()
             .add_event::<BulletFiredEvent>()
+            .add_event::<PickupEvent>()
             .add_system_set(
-                SystemSet::on_enter(AppState::InGame).with_system(spawn_player.system()),
+                SystemSet::on_enter(AppState::InGame).with_system(spawn_camera.system()),
             )
             .add_system_set(
                 SystemSet::on_update(AppState::InGame)
                     .with_system(camera_follow_player.system())
                     .with_system(player_jumps.system())
                     .with_system(player_controller.system())
-                    .with_system(jump_reset.system())
+                    .with_system(detect_ground.system())
+                    .with_system(apply_jumps.system())
                     .with_system(death_by_height.system())
                     .with_system(death_by_enemy.system())
+                    .with_system(update_weapons.system())
+                    .with_system(switch_weapon.system())
                     .with_system(fire_controller.system())
                     .with_system(kill_on_contact.system())
                     .with_system(destroy_bullet_on_contact.system())
                     .with_system(on_living_being_hit.system())
+                    .with_system(invulnerability_countdown.system())
                     .with_system(on_living_being_dead.system())
-                    .with_system(on_bullet_fired.system()),
+                    .with_system(activate_checkpoints.system())
+                    .with_system(collect_pickups.system())
+                    .with_system(apply_pickups.system())
+                    .with_system(tick_power_ups.system())
+                    .with_system(on_bullet_fired.system())
+                    .with_system(despawn_spent_bullets.system()),
             );
     }
 }
 
-pub fn spawn_player(mut commands: Commands, materials: Res<Materials>) {
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn_bundle(new_camera_2d()).insert(GameCamera);
+}
+
+pub fn insert_player_at(commands: &mut Commands, materials: &Res<Materials>, x: f32, y: f32) {
     let rigid_body = RigidBodyBundle {
-        position: Vec2::new(0., 2.).into(),
+        position: Vec2::new(x, y).into(),
         mass_properties: RigidBodyMassPropsFlags::ROTATION_LOCKED.into(),
         activation: RigidBodyActivation::cannot_sleep().into(),
         forces: RigidBodyForces {
-            gravity_scale: 3.,
+            gravity_scale: PLAYER_GRAVITY_SCALE,
             ..Default::default()
         }.into(),
         ..Default::default()
@@ -53,6 +74,7 @@ pub fn spawn_player(mut commands: Commands, materials: Res<Materials>) {
         shape: ColliderShape::round_cuboid(0.35, 0.35, 0.1).into(),
         flags: ColliderFlags {
             active_events: ActiveEvents::CONTACT_EVENTS,
+            collision_groups: InteractionGroups::new(PLAYER_GROUP, u32::MAX),
             ..Default::default()
         }.into(),
         ..Default::default()
@@ -70,85 +92,80 @@ pub fn spawn_player(mut commands: Commands, materials: Res<Materials>) {
         .insert_bundle(collider)
         .insert(RigidBodyPositionSync::Discrete)
         .insert(LivingBeing)
+        .insert(Health::new(3, 1.5))
         .insert(Player {
-            speed: 7.,
+            speed: PLAYER_SPEED,
             facing_direction: GameDirection::Right,
         })
-        .insert(Jumper {
-            jump_impulse: 14.,
-            is_jumping: false,
-        });
-    commands.spawn_bundle(new_camera_2d());
+        .insert(Jumper::new(PLAYER_JUMP_IMPULSE, 0.1, 0.15, 0.5))
+        .insert(Weapon::new(WeaponKind::Pistol))
+        .insert(Holster::default())
+        .insert(PowerUps::default());
 }
 
 pub fn player_jumps(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut players: Query<(&mut Jumper, &mut RigidBodyVelocityComponent), With<Player>>,
+    actions: Res<Input<GameAction>>,
+    mut players: Query<&mut Jumper, With<Player>>,
 ) {
-    for (mut jumper, mut velocity) in players.iter_mut() {
-        if keyboard_input.pressed(KeyCode::Up) && !jumper.is_jumping {
-            velocity.linvel = Vec2::new(0., jumper.jump_impulse).into();
-            jumper.is_jumping = true
+    for mut jumper in players.iter_mut() {
+        if actions.just_pressed(GameAction::Jump) {
+            jumper.request_jump();
         }
+        jumper.is_holding_jump = actions.pressed(GameAction::Jump);
     }
 }
 
 pub fn player_controller(
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: Res<Input<GameAction>>,
     mut players: Query<(&mut Player, &mut RigidBodyVelocityComponent)>,
 ) {
     for (mut player, mut velocity) in players.iter_mut() {
-        if keyboard_input.pressed(KeyCode::Left) {
+        if actions.pressed(GameAction::MoveLeft) {
             velocity.linvel = Vec2::new(-player.speed, velocity.linvel.y).into();
             player.facing_direction = GameDirection::Left
         }
-        if keyboard_input.pressed(KeyCode::Right) {
+        if actions.pressed(GameAction::MoveRight) {
             velocity.linvel = Vec2::new(player.speed, velocity.linvel.y).into();
             player.facing_direction = GameDirection::Right
         }
     }
 }
 
+/// Fires the weapon in hand, automatic weapons for as long as the action is held.
 pub fn fire_controller(
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: Res<Input<GameAction>>,
     mut send_fire_event: EventWriter<BulletFiredEvent>,
-    players: Query<(&Player, &RigidBodyPositionComponent), With<Player>>,
+    mut players: Query<(&Player, &RigidBodyPositionComponent, &mut Weapon)>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Space) {
-        for (player, position) in players.iter() {
-            let event = BulletFiredEvent {
-                position: Vec2::new(
-                    position.position.translation.x,
-                    position.position.translation.y,
-                ),
-                direction: player.facing_direction,
-            };
-            send_fire_event.send(event);
+    for (player, position, mut weapon) in players.iter_mut() {
+        let triggered = if weapon.automatic {
+            actions.pressed(GameAction::Fire)
+        } else {
+            actions.just_pressed(GameAction::Fire)
+        };
+        if !triggered {
+            continue;
         }
-    }
-}
-
-pub fn jump_reset(
-    mut query: Query<(Entity, &mut Jumper)>,
-    mut contact_events: EventReader<ContactEvent>,
-) {
-    for contact_event in contact_events.iter() {
-        for (entity, mut jumper) in query.iter_mut() {
-            set_jumping_false_if_touching_floor(entity, &mut jumper, contact_event);
-        }
-    }
-}
-
-fn set_jumping_false_if_touching_floor(entity: Entity, jumper: &mut Jumper, event: &ContactEvent) {
-    if let ContactEvent::Started(h1, h2) = event {
-        if h1.entity() == entity || h2.entity() == entity {
-            jumper.is_jumping = false
+        let aim = match player.facing_direction {
+            GameDirection::Left => Vec2::new(-1., 0.),
+            GameDirection::Right => Vec2::new(1., 0.),
+        };
+        let translation = position.position.translation;
+        for direction in weapon.fire(aim) {
+            send_fire_event.send(BulletFiredEvent {
+                position: Vec2::new(translation.x, translation.y),
+                direction,
+                speed: weapon.projectile_speed,
+                damage: weapon.damage,
+                bounces: weapon.bounces,
+                faction: Faction::Player,
+            });
         }
     }
 }
 
 fn camera_follow_player(
-    mut cameras: Query<&mut Transform, With<Camera>>,
+    mut cameras: Query<&mut Transform, With<GameCamera>>,
     players: Query<&RigidBodyPositionComponent, With<Player>>,
 ) {
     for player in players.iter() {
@@ -161,18 +178,24 @@ fn camera_follow_player(
 
 pub fn death_by_enemy(
     mut send_player_hit: EventWriter<LivingBeingHitEvent>,
-    players: Query<Entity, With<Player>>,
-    enemies: Query<Entity, With<Enemy>>,
+    players: Query<(Entity, &RigidBodyPositionComponent), With<Player>>,
+    enemies: Query<(Entity, &RigidBodyPositionComponent), With<Enemy>>,
     mut contact_events: EventReader<ContactEvent>,
 ) {
     for contact_event in contact_events.iter() {
         if let ContactEvent::Started(h1, h2) = contact_event {
-            for player in players.iter() {
-                for enemy in enemies.iter() {
+            for (player, player_position) in players.iter() {
+                for (enemy, enemy_position) in enemies.iter() {
                     if (h1.entity() == player && h2.entity() == enemy)
                         || (h1.entity() == enemy && h2.entity() == player)
                     {
-                        send_player_hit.send(LivingBeingHitEvent { entity: player })
+                        let away = player_position.position.translation.x
+                            - enemy_position.position.translation.x;
+                        send_player_hit.send(LivingBeingHitEvent {
+                            entity: player,
+                            damage: 1,
+                            knockback: Vec2::new(8. * away.signum(), 8.),
+                        })
                     }
                 }
             }
@@ -0,0 +1,82 @@
+use bevy::prelude::*;
+
+use super::{LevelSeed, ResumedRun};
+
+/// State of the current run, carried from one level to the next.
+pub struct LevelProgress {
+    /// Index of the current level, starting at 0.
+    pub level: usize,
+    pub score: u32,
+    pub lives: u32,
+    /// Seconds played in the levels completed so far.
+    pub time: f32,
+}
+
+impl Default for LevelProgress {
+    fn default() -> Self {
+        LevelProgress {
+            level: 0,
+            score: 0,
+            lives: 3,
+            time: 0.,
+        }
+    }
+}
+
+/// Time spent playing the current level, pauses excluded.
+#[derive(Default)]
+pub struct LevelTime {
+    pub elapsed: f32,
+    /// Time in seconds a good run of the current level should take.
+    pub par: f32,
+}
+
+pub fn reset_level_time(mut level_time: ResMut<LevelTime>, resumed: Option<Res<ResumedRun>>) {
+    level_time.elapsed = resumed.map_or(0., |resumed| resumed.0.level_time);
+}
+
+pub fn tick_level_time(time: Res<Time>, mut level_time: ResMut<LevelTime>) {
+    level_time.elapsed += time.delta_seconds();
+}
+
+pub struct Difficulty {
+    pub width: usize,
+    /// Monsters whose `min_level` is above this one don't appear.
+    pub monster_level: usize,
+    /// Chance, in percent, for a column to spawn an enemy.
+    pub enemy_chance: u32,
+    /// Chance, in percent, for the terrain to stay flat between two columns.
+    pub flat_chance: u32,
+    /// Chance, in percent, for a column to start a pit.
+    pub pit_chance: u32,
+    pub max_pit_width: usize,
+    pub platform_chance: u32,
+    pub overhang_chance: u32,
+    /// Chance, in percent, for a column to hold a pickup.
+    pub pickup_chance: u32,
+    /// Whether the level ends with a boss arena.
+    pub boss: bool,
+}
+
+impl LevelProgress {
+    pub fn difficulty(&self) -> Difficulty {
+        let level = self.level as u32;
+        Difficulty {
+            width: 150 + 25 * self.level.min(10),
+            monster_level: self.level,
+            enemy_chance: (9 + 2 * level).min(30),
+            flat_chance: 76 - (4 * level).min(36),
+            pit_chance: (3 + level).min(10),
+            max_pit_width: 3 + self.level.min(2),
+            platform_chance: 4,
+            overhang_chance: 3,
+            pickup_chance: 4,
+            boss: self.level % 3 == 2,
+        }
+    }
+
+    /// Seed of the current level. The first level uses the run's seed as is.
+    pub fn level_seed(&self, seed: &LevelSeed) -> u64 {
+        seed.value ^ (self.level as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+}
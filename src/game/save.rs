@@ -0,0 +1,191 @@
+use bevy::{asset::LoadState, prelude::*};
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::super::AppState;
+use super::{
+    LevelFile, LevelProgress, LevelSeed, LevelTime, Monster, Pickup, PickupKind, Player,
+    RespawnPoint, Score,
+};
+use crate::storage;
+
+const SAVED_RUN_KEY: &str = "run";
+
+/// A run saved from the pause menu, resumed with Continue from the main menu.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedRun {
+    pub seed: u64,
+    /// Asset path of the level passed with `--level`, if the run wasn't generated.
+    pub level_file: Option<String>,
+    pub level: usize,
+    /// Points banked in the levels completed so far.
+    pub score: u32,
+    pub lives: u32,
+    pub time: f32,
+    pub level_score: Score,
+    pub level_time: f32,
+    pub respawn_point: (f32, f32),
+    pub player_position: (f32, f32),
+    /// Position and name of each monster.
+    pub monsters: Vec<(f32, f32, String)>,
+    /// Pickups not collected yet.
+    #[serde(default)]
+    pub pickups: Vec<(f32, f32, PickupKind)>,
+}
+
+impl SavedRun {
+    pub fn load() -> Option<Self> {
+        storage::load_ron(SAVED_RUN_KEY, "saved run")
+    }
+
+    pub fn exists() -> bool {
+        storage::load(SAVED_RUN_KEY).is_some()
+    }
+
+    pub fn save(&self) {
+        storage::save_ron(SAVED_RUN_KEY, "the run", self);
+    }
+
+    pub fn delete() {
+        storage::remove(SAVED_RUN_KEY);
+    }
+}
+
+pub struct SaveRunEvent;
+
+pub struct ContinueRunEvent;
+
+/// The saved run being resumed, replacing the level's start when it spawns.
+pub struct ResumedRun(pub SavedRun);
+
+/// A saved run waiting for its level file to load before it is resumed.
+pub struct PendingRun(pub SavedRun);
+
+fn translation(position: &RigidBodyPositionComponent) -> (f32, f32) {
+    let translation = position.position.translation;
+    (translation.x, translation.y)
+}
+
+pub fn save_run(
+    mut save_run_events: EventReader<SaveRunEvent>,
+    seed: Res<LevelSeed>,
+    progress: Res<LevelProgress>,
+    score: Res<Score>,
+    level_time: Res<LevelTime>,
+    respawn_point: Res<RespawnPoint>,
+    level_file: Option<Res<LevelFile>>,
+    asset_server: Res<AssetServer>,
+    players: Query<&RigidBodyPositionComponent, With<Player>>,
+    monsters: Query<(&RigidBodyPositionComponent, &Monster)>,
+    pickups: Query<(&RigidBodyPositionComponent, &Pickup)>,
+) {
+    if save_run_events.iter().count() == 0 {
+        return;
+    }
+    let player = match players.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let level_file = level_file.and_then(|file| {
+        let path = asset_server.get_handle_path(&file.0)?;
+        path.path().to_str().map(String::from)
+    });
+    SavedRun {
+        seed: seed.value,
+        level_file,
+        level: progress.level,
+        score: progress.score,
+        lives: progress.lives,
+        time: progress.time,
+        level_score: score.clone(),
+        level_time: level_time.elapsed,
+        respawn_point: (respawn_point.0.x, respawn_point.0.y),
+        player_position: translation(player),
+        monsters: monsters
+            .iter()
+            .map(|(position, monster)| {
+                let (x, y) = translation(position);
+                (x, y, monster.name.clone())
+            })
+            .collect(),
+        pickups: pickups
+            .iter()
+            .map(|(position, pickup)| {
+                let (x, y) = translation(position);
+                (x, y, pickup.0)
+            })
+            .collect(),
+    }
+    .save();
+}
+
+pub fn continue_run(
+    mut commands: Commands,
+    mut continue_run_events: EventReader<ContinueRunEvent>,
+    mut seed: ResMut<LevelSeed>,
+    mut progress: ResMut<LevelProgress>,
+    asset_server: Res<AssetServer>,
+) {
+    if continue_run_events.iter().count() == 0 {
+        return;
+    }
+    let run = match SavedRun::load() {
+        Some(run) => run,
+        None => return,
+    };
+    seed.value = run.seed;
+    *progress = LevelProgress {
+        level: run.level,
+        score: run.score,
+        lives: run.lives,
+        time: run.time,
+    };
+    match &run.level_file {
+        Some(path) => commands.insert_resource(LevelFile(asset_server.load(path.as_str()))),
+        // The level passed with `--level` would replace the generated one. It is put back on
+        // returning to the main menu.
+        None => commands.remove_resource::<LevelFile>(),
+    }
+    commands.insert_resource(PendingRun(run));
+}
+
+/// Resumes the pending run once its level file is loaded, as the level would be generated
+/// instead otherwise.
+pub fn resume_pending_run(
+    mut commands: Commands,
+    pending: Option<Res<PendingRun>>,
+    level_file: Option<Res<LevelFile>>,
+    asset_server: Res<AssetServer>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    let pending = match pending {
+        Some(pending) => pending,
+        None => return,
+    };
+    if pending.0.level_file.is_some() {
+        match level_file.map(|file| asset_server.get_load_state(&file.0)) {
+            Some(LoadState::Loaded) => {}
+            Some(LoadState::Failed) | None => {
+                warn!("Couldn't load the level of the saved run");
+                commands.remove_resource::<PendingRun>();
+                return;
+            }
+            Some(_) => return,
+        }
+    }
+    commands.remove_resource::<PendingRun>();
+    commands.insert_resource(ResumedRun(pending.0.clone()));
+    app_state
+        .set(AppState::InGame)
+        .expect("Couldn't switch state to InGame");
+}
+
+/// Drops a run still loading when another game is started from the main menu.
+pub fn cancel_pending_run(mut commands: Commands) {
+    commands.remove_resource::<PendingRun>();
+}
+
+/// A run that ended can't be continued.
+pub fn delete_saved_run() {
+    SavedRun::delete();
+}
@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::ResumedRun;
+
+const KILL_POINTS: u32 = 100;
+const LEVEL_POINTS: u32 = 1000;
+/// Points for each second the level was finished under its par time.
+const TIME_BONUS_POINTS: u32 = 20;
+const NO_DAMAGE_POINTS: u32 = 500;
+/// Points for a boss, on top of those for killing an enemy.
+const BOSS_POINTS: u32 = 1500;
+
+pub enum ScoreEvent {
+    EnemyKilled,
+    BossDefeated,
+    LevelCompleted,
+    TimeBonus { seconds_under_par: u32 },
+    NoDamageBonus,
+}
+
+/// Points earned in the current level by source, banked into `LevelProgress::score` when moving
+/// on to the next level.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Score {
+    pub kills: u32,
+    pub completion: u32,
+    pub time_bonus: u32,
+    pub no_damage_bonus: u32,
+    /// Whether the player was hurt during the level, losing the no-damage bonus.
+    pub damage_taken: bool,
+}
+
+impl Score {
+    pub fn total(&self) -> u32 {
+        self.kills + self.completion + self.time_bonus + self.no_damage_bonus
+    }
+}
+
+pub fn reset_score(mut score: ResMut<Score>, resumed: Option<Res<ResumedRun>>) {
+    *score = match resumed {
+        Some(resumed) => resumed.0.level_score.clone(),
+        None => Score::default(),
+    };
+}
+
+pub fn on_score_event(mut score_events: EventReader<ScoreEvent>, mut score: ResMut<Score>) {
+    for event in score_events.iter() {
+        match event {
+            ScoreEvent::EnemyKilled => score.kills += KILL_POINTS,
+            ScoreEvent::BossDefeated => score.kills += BOSS_POINTS,
+            ScoreEvent::LevelCompleted => score.completion += LEVEL_POINTS,
+            ScoreEvent::TimeBonus { seconds_under_par } => {
+                score.time_bonus += seconds_under_par * TIME_BONUS_POINTS
+            }
+            ScoreEvent::NoDamageBonus => score.no_damage_bonus += NO_DAMAGE_POINTS,
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use serde::Deserialize;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Tile {
+    Empty,
+    Solid,
+    /// Platform that can be jumped through from below.
+    OneWay,
+}
+
+/// Terrain of a level. Tile `(x, y)` is centered on world position `(x, y)`; row 0 is below the
+/// ground and stays empty so that falling into a pit is deadly.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(from = "Vec<String>")]
+pub struct TileGrid {
+    width: usize,
+    height: usize,
+    tiles: Vec<Tile>,
+}
+
+/// Horizontal run of identical tiles on row `y`, from column `from` up to, but excluding, `to`.
+pub struct TileRun {
+    pub tile: Tile,
+    pub y: usize,
+    pub from: usize,
+    pub to: usize,
+}
+
+impl TileGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        TileGrid {
+            width,
+            height,
+            tiles: vec![Tile::Empty; width * height],
+        }
+    }
+
+    /// Fills every column from row 1 up to its height. A height of 0 leaves a pit.
+    pub fn from_heights(heights: &[usize], headroom: usize) -> Self {
+        let max = heights.iter().max().copied().unwrap_or(0);
+        let mut grid = TileGrid::new(heights.len(), max + headroom + 1);
+        heights.iter().enumerate().for_each(|(x, height)| {
+            (1..=*height).for_each(|y| grid.set(x, y, Tile::Solid));
+        });
+        grid
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Tiles outside of the grid are empty.
+    pub fn get(&self, x: usize, y: usize) -> Tile {
+        if x < self.width && y < self.height {
+            self.tiles[y * self.width + x]
+        } else {
+            Tile::Empty
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, tile: Tile) {
+        if x < self.width && y < self.height {
+            self.tiles[y * self.width + x] = tile;
+        }
+    }
+
+    pub fn is_empty(&self, x: usize, y: usize) -> bool {
+        self.get(x, y) == Tile::Empty
+    }
+
+    /// Rows, from the bottom, on which something can stand in column `x`.
+    pub fn standable_rows(&self, x: usize) -> Vec<usize> {
+        (1..self.height)
+            .filter(|y| !self.is_empty(x, *y) && self.is_empty(x, y + 1))
+            .collect()
+    }
+
+    pub fn runs(&self) -> Vec<TileRun> {
+        let mut runs = Vec::new();
+        (0..self.height).for_each(|y| {
+            let mut start: Option<(usize, Tile)> = None;
+            (0..=self.width).for_each(|x| {
+                let tile = self.get(x, y);
+                match start {
+                    Some((from, run_tile)) if run_tile != tile => {
+                        runs.push(TileRun {
+                            tile: run_tile,
+                            y,
+                            from,
+                            to: x,
+                        });
+                        start = None;
+                    }
+                    _ => (),
+                }
+                if start.is_none() && tile != Tile::Empty {
+                    start = Some((x, tile));
+                }
+            });
+        });
+        runs
+    }
+}
+
+/// Rows are listed from the top of the level down to the ground, `#` being a solid tile and `=`
+/// a one-way platform.
+impl From<Vec<String>> for TileGrid {
+    fn from(rows: Vec<String>) -> Self {
+        let width = rows.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+        let mut grid = TileGrid::new(width, rows.len() + 1);
+        rows.iter().enumerate().for_each(|(index, row)| {
+            let y = rows.len() - index;
+            row.chars().enumerate().for_each(|(x, c)| {
+                let tile = match c {
+                    '#' => Tile::Solid,
+                    '=' => Tile::OneWay,
+                    _ => Tile::Empty,
+                };
+                grid.set(x, y, tile);
+            });
+        });
+        grid
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::{HashSet, VecDeque};
+
+use bevy::math::Vec2;
+
+use super::{Level, Tile, TileGrid, PLAYER_GRAVITY_SCALE, PLAYER_JUMP_IMPULSE, PLAYER_SPEED};
+
+const GRAVITY: f32 = 9.81;
+const PLAYER_HALF_SIZE: f32 = 0.44;
+const TIME_STEP: f32 = 1. / 60.;
+const MAX_FLIGHT_TIME: f32 = 3.;
+/// Delays before steering in the air, letting the player land on close tiles.
+const STEERING_DELAYS: [f32; 9] = [0., 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8];
+
+#[derive(Debug, PartialEq)]
+pub enum LevelError {
+    UnreachableWinningZone,
+    EnemyInTerrain { x: usize, y: usize },
+}
+
+pub fn validate_level(level: &Level) -> Result<(), LevelError> {
+    for (x, y) in level.enemies.iter() {
+        if !level.tiles.is_empty(*x, *y) {
+            return Err(LevelError::EnemyInTerrain { x: *x, y: *y });
+        }
+    }
+    if is_winning_zone_reachable(level) {
+        Ok(())
+    } else {
+        Err(LevelError::UnreachableWinningZone)
+    }
+}
+
+/// Explores every tile the player can stand on, following the jump arcs and falls a player
+/// can perform from each of them.
+pub fn is_winning_zone_reachable(level: &Level) -> bool {
+    let first = match standing_tile(&level.tiles, level.player_start) {
+        Some(tile) => tile,
+        None => return false,
+    };
+
+    let mut visited: HashSet<(usize, usize)> = HashSet::new();
+    let mut queue: VecDeque<(usize, usize)> = VecDeque::new();
+    visited.insert(first);
+    queue.push_back(first);
+    while let Some((x, y)) = queue.pop_front() {
+        if x as f32 + 1. >= level.winning_zone {
+            return true;
+        }
+        for next in reachable_from(&level.tiles, x, y) {
+            if visited.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    false
+}
+
+fn reachable_from(tiles: &TileGrid, x: usize, y: usize) -> Vec<(usize, usize)> {
+    let position = Vec2::new(x as f32, y as f32 + 0.5 + PLAYER_HALF_SIZE + 0.01);
+    let mut reachable = Vec::new();
+    for direction in [-1., 1.] {
+        let neighbour = x as isize + direction as isize;
+        if neighbour >= 0 && is_standable(tiles, neighbour as usize, y) {
+            reachable.push((neighbour as usize, y));
+        }
+        let walk = simulate(tiles, position, direction * PLAYER_SPEED, 0., 0.);
+        reachable.extend(walk);
+        for delay in STEERING_DELAYS {
+            let jump = simulate(
+                tiles,
+                position,
+                direction * PLAYER_SPEED,
+                delay,
+                PLAYER_JUMP_IMPULSE,
+            );
+            reachable.extend(jump);
+        }
+    }
+    reachable
+}
+
+fn is_standable(tiles: &TileGrid, x: usize, y: usize) -> bool {
+    !tiles.is_empty(x, y) && tiles.is_empty(x, y + 1)
+}
+
+fn standing_tile(tiles: &TileGrid, (x, y): (f32, f32)) -> Option<(usize, usize)> {
+    if x < 0. {
+        return None;
+    }
+    let column = x.round() as usize;
+    tiles
+        .standable_rows(column)
+        .into_iter()
+        .filter(|row| (*row as f32) < y)
+        .max()
+        .map(|row| (column, row))
+}
+
+/// Moves the player's box through the tiles until it lands, returning the tile it landed on.
+/// Walking (no jump impulse) gives up if the player doesn't fall off a ledge quickly.
+fn simulate(
+    tiles: &TileGrid,
+    start: Vec2,
+    horizontal_speed: f32,
+    steering_delay: f32,
+    jump_impulse: f32,
+) -> Option<(usize, usize)> {
+    let gravity = GRAVITY * PLAYER_GRAVITY_SCALE;
+    let mut position = start;
+    let mut vertical_speed = jump_impulse;
+    let mut time = 0.;
+    while time < MAX_FLIGHT_TIME {
+        time += TIME_STEP;
+        let has_fallen = start.y - position.y > 0.25;
+        if jump_impulse == 0. && time > 0.2 && start.y - position.y < 0.01 {
+            return None;
+        }
+
+        if time >= steering_delay {
+            let next = Vec2::new(position.x + horizontal_speed * TIME_STEP, position.y);
+            if overlapping_tile(tiles, next, None).is_none() {
+                position = next;
+            }
+        }
+
+        vertical_speed -= gravity * TIME_STEP;
+        let next = Vec2::new(position.x, position.y + vertical_speed * TIME_STEP);
+        let bottom = if vertical_speed < 0. {
+            Some(position.y - PLAYER_HALF_SIZE)
+        } else {
+            None
+        };
+        match overlapping_tile(tiles, next, bottom) {
+            Some((x, y)) if vertical_speed < 0. && (has_fallen || jump_impulse > 0.) => {
+                return Some(landing_tile(tiles, position.x, y).unwrap_or((x, y)));
+            }
+            Some(_) => vertical_speed = 0.,
+            None => position = next,
+        }
+
+        if position.y < 0. {
+            return None;
+        }
+    }
+    None
+}
+
+/// Prefers the tile right under the player's center when it lands across two tiles.
+fn landing_tile(tiles: &TileGrid, x: f32, y: usize) -> Option<(usize, usize)> {
+    let column = x.round();
+    if column >= 0. && is_standable(tiles, column as usize, y) {
+        Some((column as usize, y))
+    } else {
+        None
+    }
+}
+
+/// Returns a tile overlapping the player's box centered on `position`. One-way platforms only
+/// count when `falling_from` is set and the box was above them.
+fn overlapping_tile(
+    tiles: &TileGrid,
+    position: Vec2,
+    falling_from: Option<f32>,
+) -> Option<(usize, usize)> {
+    let min = position - Vec2::splat(PLAYER_HALF_SIZE);
+    let max = position + Vec2::splat(PLAYER_HALF_SIZE);
+    for x in min.x.round() as isize..=max.x.round() as isize {
+        for y in min.y.round() as isize..=max.y.round() as isize {
+            if x < 0 || y < 0 {
+                continue;
+            }
+            let (x, y) = (x as usize, y as usize);
+            let blocks = match tiles.get(x, y) {
+                Tile::Empty => false,
+                Tile::Solid => true,
+                Tile::OneWay => falling_from.map_or(false, |bottom| bottom >= y as f32 + 0.49),
+            };
+            if blocks {
+                return Some((x, y));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{generate_level, LevelProgress};
+    use super::*;
+
+    /// The first level, a boss level, and levels with the widest pits and most enemies.
+    const LEVELS: [usize; 4] = [0, 2, 5, 10];
+
+    fn assert_winnable(seeds: std::ops::Range<u64>) {
+        for level in LEVELS {
+            let difficulty = LevelProgress {
+                level,
+                ..Default::default()
+            }
+            .difficulty();
+            for seed in seeds.clone() {
+                assert_eq!(
+                    validate_level(&generate_level(seed, &difficulty)),
+                    Ok(()),
+                    "level {} of seed {}",
+                    level,
+                    seed
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn generated_levels_are_winnable() {
+        assert_winnable(0..50);
+    }
+
+    /// Takes a long while: run with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn generated_levels_are_winnable_for_thousands_of_seeds() {
+        assert_winnable(0..5000);
+    }
+
+    #[test]
+    fn generated_levels_are_deterministic() {
+        for level in LEVELS {
+            let difficulty = LevelProgress {
+                level,
+                ..Default::default()
+            }
+            .difficulty();
+            for seed in [0, 42, 1234] {
+                assert_eq!(
+                    generate_level(seed, &difficulty),
+                    generate_level(seed, &difficulty),
+                    "level {} of seed {}",
+                    level,
+                    seed
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn unreachable_winning_zone_is_detected() {
+        let mut level = generate_level(0, &LevelProgress::default().difficulty());
+        level.winning_zone += 20.;
+        assert_eq!(validate_level(&level), Err(LevelError::UnreachableWinningZone));
+    }
+}
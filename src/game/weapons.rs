@@ -0,0 +1,215 @@
+use bevy::prelude::*;
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::actions::GameAction;
+
+use super::Player;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WeaponKind {
+    Pistol,
+    Shotgun,
+    RapidFire,
+    Bouncing,
+    /// Slow shots fired by monsters.
+    Spit,
+}
+
+impl WeaponKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            WeaponKind::Pistol => "Pistol",
+            WeaponKind::Shotgun => "Shotgun",
+            WeaponKind::RapidFire => "Rapid Fire",
+            WeaponKind::Bouncing => "Bouncer",
+            WeaponKind::Spit => "Spit",
+        }
+    }
+}
+
+/// The weapon in hand, firing bullets when its cooldown and reload allow it.
+#[derive(Component, Clone)]
+pub struct Weapon {
+    pub kind: WeaponKind,
+    /// Shots per second.
+    pub fire_rate: f32,
+    /// Whether holding the trigger keeps firing.
+    pub automatic: bool,
+    pub magazine_size: u32,
+    /// Rounds left in the magazine.
+    pub loaded: u32,
+    /// Rounds left to reload from, unlimited when `None`.
+    pub reserve: Option<u32>,
+    /// Seconds taken to refill the magazine.
+    pub reload_time: f32,
+    pub projectile_speed: f32,
+    /// Angle in radians covered by the projectiles of one shot.
+    pub spread: f32,
+    pub projectile_count: u32,
+    /// Damage dealt by each projectile.
+    pub damage: u32,
+    /// Walls a projectile bounces off before vanishing.
+    pub bounces: u32,
+    /// Seconds before the next shot.
+    cooldown: f32,
+    /// Seconds before the magazine is refilled, while reloading.
+    reloading: Option<f32>,
+}
+
+impl Weapon {
+    pub fn new(kind: WeaponKind) -> Self {
+        let (fire_rate, automatic, magazine_size, reserve, reload_time) = match kind {
+            WeaponKind::Pistol => (3., false, 8, None, 1.),
+            WeaponKind::Shotgun => (1.2, false, 2, Some(12), 1.5),
+            WeaponKind::RapidFire => (10., true, 30, Some(60), 2.),
+            WeaponKind::Bouncing => (2., false, 6, Some(18), 1.2),
+            WeaponKind::Spit => (0.6, false, 3, None, 2.),
+        };
+        let (projectile_speed, spread, projectile_count, damage, bounces) = match kind {
+            WeaponKind::Pistol => (14., 0., 1, 1, 0),
+            WeaponKind::Shotgun => (12., 0.5, 5, 1, 0),
+            WeaponKind::RapidFire => (18., 0.1, 1, 1, 0),
+            WeaponKind::Bouncing => (10., 0., 1, 2, 3),
+            WeaponKind::Spit => (8., 0.1, 1, 1, 0),
+        };
+        Weapon {
+            kind,
+            fire_rate,
+            automatic,
+            magazine_size,
+            loaded: magazine_size,
+            reserve,
+            reload_time,
+            projectile_speed,
+            spread,
+            projectile_count,
+            damage,
+            bounces,
+            cooldown: 0.,
+            reloading: None,
+        }
+    }
+
+    pub fn is_reloading(&self) -> bool {
+        self.reloading.is_some()
+    }
+
+    pub fn can_fire(&self) -> bool {
+        self.cooldown <= 0. && !self.is_reloading() && self.loaded > 0
+    }
+
+    /// Advances the cooldown and the reload, refilling the magazine once reloaded.
+    pub fn tick(&mut self, delta: f32) {
+        self.cooldown = (self.cooldown - delta).max(0.);
+        if let Some(remaining) = self.reloading {
+            let remaining = remaining - delta;
+            if remaining > 0. {
+                self.reloading = Some(remaining);
+                return;
+            }
+            self.reloading = None;
+            let missing = self.magazine_size - self.loaded;
+            let rounds = self.reserve.map_or(missing, |reserve| reserve.min(missing));
+            self.loaded += rounds;
+            if let Some(reserve) = self.reserve.as_mut() {
+                *reserve -= rounds;
+            }
+        }
+    }
+
+    /// Starts reloading unless the magazine is full or there is nothing to reload from.
+    pub fn reload(&mut self) {
+        if self.loaded < self.magazine_size && self.reserve != Some(0) && !self.is_reloading() {
+            self.reloading = Some(self.reload_time);
+        }
+    }
+
+    /// Uses a round, returning the direction of each projectile fired towards `aim`.
+    pub fn fire(&mut self, aim: Vec2) -> Vec<Vec2> {
+        if !self.can_fire() {
+            return Vec::new();
+        }
+        self.loaded -= 1;
+        self.cooldown = 1. / self.fire_rate;
+        if self.loaded == 0 {
+            self.reload();
+        }
+
+        let mut rng = thread_rng();
+        (0..self.projectile_count)
+            .map(|index| {
+                // Several projectiles fan out evenly, a single one deviates randomly.
+                let angle = if self.projectile_count > 1 {
+                    self.spread * (index as f32 / (self.projectile_count - 1) as f32 - 0.5)
+                } else if self.spread > 0. {
+                    rng.gen_range(-0.5..0.5) * self.spread
+                } else {
+                    0.
+                };
+                Mat2::from_angle(angle) * aim
+            })
+            .collect()
+    }
+
+    pub fn add_ammo(&mut self, rounds: u32) {
+        if let Some(reserve) = self.reserve.as_mut() {
+            *reserve += rounds;
+        }
+        if self.loaded == 0 {
+            self.reload();
+        }
+    }
+
+    /// Rounds loaded and in reserve, as shown in the HUD.
+    pub fn ammo_label(&self) -> String {
+        match (self.is_reloading(), self.reserve) {
+            (true, _) => "reloading".to_string(),
+            (false, Some(reserve)) => format!("{}/{}", self.loaded, reserve),
+            (false, None) => self.loaded.to_string(),
+        }
+    }
+}
+
+/// Weapons carried besides the one in hand, in switching order.
+#[derive(Component, Default)]
+pub struct Holster(pub Vec<Weapon>);
+
+/// Takes a weapon of the given kind: another magazine for a weapon already carried, or a new
+/// weapon put in hand.
+pub fn pick_up_weapon(weapon: &mut Weapon, holster: &mut Holster, kind: WeaponKind) {
+    if weapon.kind == kind {
+        let rounds = weapon.magazine_size;
+        weapon.add_ammo(rounds);
+    } else if let Some(carried) = holster.0.iter_mut().find(|carried| carried.kind == kind) {
+        let rounds = carried.magazine_size;
+        carried.add_ammo(rounds);
+    } else {
+        let previous = std::mem::replace(weapon, Weapon::new(kind));
+        holster.0.insert(0, previous);
+    }
+}
+
+pub fn update_weapons(time: Res<Time>, mut weapons: Query<&mut Weapon>) {
+    for mut weapon in weapons.iter_mut() {
+        weapon.tick(time.delta_seconds());
+    }
+}
+
+/// Puts the weapon in hand at the back of the holster and draws the next one.
+pub fn switch_weapon(
+    actions: Res<Input<GameAction>>,
+    mut players: Query<(&mut Weapon, &mut Holster), With<Player>>,
+) {
+    if !actions.just_pressed(GameAction::SwitchWeapon) {
+        return;
+    }
+    for (mut weapon, mut holster) in players.iter_mut() {
+        if holster.0.is_empty() {
+            continue;
+        }
+        let next = holster.0.remove(0);
+        let previous = std::mem::replace(&mut *weapon, next);
+        holster.0.push(previous);
+    }
+}
@@ -1,6 +1,11 @@
 use bevy::{prelude::*, window::WindowMode};
 use wasm_bindgen::prelude::*;
 
+mod actions;
+use actions::ActionsPlugin;
+
+mod storage;
+
 mod game;
 use game::GamePlugin;
 
@@ -11,8 +16,19 @@ use menus::MenusPlugin;
 pub enum AppState {
     MainMenu,
     InGame,
+    Paused,
     GameOver,
     BetweenLevels,
+    Options,
+    Controls,
+    HighScores,
+}
+
+/// Returns the value following `name` on the command line, e.g. `--seed 42`.
+pub(crate) fn command_line_argument(name: &str) -> Option<String> {
+    let mut args = std::env::args();
+    args.find(|arg| arg == name)?;
+    args.next()
 }
 
 #[wasm_bindgen]
@@ -33,6 +49,7 @@ pub fn run() {
     .add_plugins(DefaultPlugins)
     .add_state(AppState::MainMenu)
     .insert_resource(ClearColor(Color::rgb(0.04, 0.04, 0.04)))
+    .add_plugin(ActionsPlugin)
     .add_plugin(MenusPlugin)
     .add_plugin(GamePlugin)
     .run();
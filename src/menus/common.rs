@@ -6,6 +6,7 @@ pub struct MenuMaterials {
     pub menu: UiColor,
     pub button: UiColor,
     pub button_hovered: UiColor,
+    pub button_focused: UiColor,
     pub button_pressed: UiColor,
     pub button_text: Color,
 }
@@ -18,24 +19,31 @@ impl FromWorld for MenuMaterials {
             menu: Color::rgb(0.15, 0.15, 0.15).into(),
             button: Color::rgb(0.15, 0.15, 0.15).into(),
             button_hovered: Color::rgb(0.25, 0.25, 0.25).into(),
+            button_focused: Color::rgb(0.2, 0.3, 0.45).into(),
             button_pressed: Color::rgb(0.35, 0.75, 0.35).into(),
             button_text: Color::WHITE,
         }
     }
 }
 
+/// The menu button that keyboard and gamepad confirm presses.
+#[derive(Component)]
+pub struct Focused;
+
 pub fn button_system(
     materials: Res<MenuMaterials>,
-    mut buttons: Query<
-        (&Interaction, &mut UiColor),
-        (Changed<Interaction>, With<Button>),
-    >,
+    mut buttons: Query<(&Interaction, &mut UiColor, Option<&Focused>), With<Button>>,
 ) {
-    for (interaction, mut material) in buttons.iter_mut() {
-        match *interaction {
-            Interaction::Clicked => *material = materials.button_pressed.clone(),
-            Interaction::Hovered => *material = materials.button_hovered.clone(),
-            Interaction::None => *material = materials.button.clone(),
+    for (interaction, mut material, focused) in buttons.iter_mut() {
+        let color = match (*interaction, focused.is_some()) {
+            (Interaction::Clicked, _) => materials.button_pressed,
+            (Interaction::Hovered, _) => materials.button_hovered,
+            (Interaction::None, true) => materials.button_focused,
+            (Interaction::None, false) => materials.button,
+        };
+        // Only write on change, to not trigger change detection every frame.
+        if material.0 != color.0 {
+            *material = color;
         }
     }
 }
@@ -116,3 +124,44 @@ pub fn button_text(
         ..Default::default()
     };
 }
+
+pub fn info_text(
+    asset_server: &Res<AssetServer>,
+    materials: &Res<MenuMaterials>,
+    label: &str,
+) -> TextBundle {
+    let mut text = button_text(asset_server, materials, label);
+    text.text.sections[0].style.font_size = 20.0;
+    text
+}
+
+pub fn compact_text(
+    asset_server: &Res<AssetServer>,
+    materials: &Res<MenuMaterials>,
+    label: &str,
+) -> TextBundle {
+    let mut text = info_text(asset_server, materials, label);
+    text.style.margin = Rect::all(Val::Px(4.0));
+    text
+}
+
+pub fn row(materials: &Res<MenuMaterials>) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(100.0), Val::Auto),
+            flex_direction: FlexDirection::Row,
+            justify_content: JustifyContent::SpaceBetween,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        color: materials.menu.clone(),
+        ..Default::default()
+    }
+}
+
+pub fn row_button(materials: &Res<MenuMaterials>) -> ButtonBundle {
+    let mut button = button(materials);
+    button.style.size = Size::new(Val::Px(180.0), Val::Auto);
+    button.style.margin = Rect::all(Val::Px(2.0));
+    button
+}
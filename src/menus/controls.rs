@@ -0,0 +1,250 @@
+use super::{
+    border, button, button_text, compact_text, menu_background, root, row, row_button, MenuButton,
+    MenuMaterials,
+};
+use crate::actions::{GameAction, GamepadInput, InputBindings, MenuAction};
+use bevy::prelude::*;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BindingDevice {
+    Keyboard,
+    Gamepad,
+}
+
+/// The binding waiting for the player to press a new key or button, if any.
+#[derive(Default)]
+pub struct Rebinding(pub Option<(GameAction, BindingDevice)>);
+
+#[derive(Component)]
+pub(super) struct ControlsMessage;
+
+const CONTROLS_HINT: &str =
+    "Click a binding, then press the new key or button, or Escape to cancel";
+
+fn binding_label(
+    bindings: &InputBindings,
+    rebinding: &Rebinding,
+    action: GameAction,
+    device: BindingDevice,
+) -> String {
+    if rebinding.0 == Some((action, device)) {
+        return match device {
+            BindingDevice::Keyboard => "Press a key...",
+            BindingDevice::Gamepad => "Press a button...",
+        }
+        .to_string();
+    }
+    match device {
+        BindingDevice::Keyboard => bindings.keys.get(&action).map(|key| format!("{:?}", key)),
+        BindingDevice::Gamepad => bindings.gamepad.get(&action).map(GamepadInput::label),
+    }
+    .unwrap_or_else(|| "-".to_string())
+}
+
+pub(super) fn setup_options_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    materials: Res<MenuMaterials>,
+) {
+    commands.spawn_bundle(UiCameraBundle::default());
+
+    commands
+        .spawn_bundle(root(&materials))
+        .with_children(|parent| {
+            parent.spawn_bundle(button_text(&asset_server, &materials, "Options"));
+            parent
+                .spawn_bundle(border(&materials))
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(menu_background(&materials))
+                        .with_children(|parent| {
+                            parent
+                                .spawn_bundle(button(&materials))
+                                .with_children(|parent| {
+                                    parent.spawn_bundle(button_text(
+                                        &asset_server,
+                                        &materials,
+                                        "Controls",
+                                    ));
+                                })
+                                .insert(MenuButton::Controls);
+                            parent
+                                .spawn_bundle(button(&materials))
+                                .with_children(|parent| {
+                                    parent.spawn_bundle(button_text(
+                                        &asset_server,
+                                        &materials,
+                                        "Back",
+                                    ));
+                                })
+                                .insert(MenuButton::BackToMainMenu);
+                        });
+                });
+        });
+}
+
+pub(super) fn setup_controls_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    materials: Res<MenuMaterials>,
+    bindings: Res<InputBindings>,
+    rebinding: Res<Rebinding>,
+) {
+    commands.spawn_bundle(UiCameraBundle::default());
+
+    let mut controls_border = border(&materials);
+    controls_border.style.size = Size::new(Val::Px(600.0), Val::Auto);
+
+    commands
+        .spawn_bundle(root(&materials))
+        .with_children(|parent| {
+            parent.spawn_bundle(button_text(&asset_server, &materials, "Controls"));
+            parent
+                .spawn_bundle(compact_text(&asset_server, &materials, CONTROLS_HINT))
+                .insert(ControlsMessage);
+            parent
+                .spawn_bundle(controls_border)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(menu_background(&materials))
+                        .with_children(|parent| {
+                            for action in GameAction::ALL {
+                                parent
+                                    .spawn_bundle(row(&materials))
+                                    .with_children(|parent| {
+                                        parent.spawn_bundle(compact_text(
+                                            &asset_server,
+                                            &materials,
+                                            action.label(),
+                                        ));
+                                        for device in
+                                            [BindingDevice::Keyboard, BindingDevice::Gamepad]
+                                        {
+                                            let label = binding_label(
+                                                &bindings, &rebinding, action, device,
+                                            );
+                                            parent
+                                                .spawn_bundle(row_button(&materials))
+                                                .with_children(|parent| {
+                                                    parent.spawn_bundle(compact_text(
+                                                        &asset_server,
+                                                        &materials,
+                                                        &label,
+                                                    ));
+                                                })
+                                                .insert(MenuButton::Rebind(action, device));
+                                        }
+                                    });
+                            }
+                            parent
+                                .spawn_bundle(row(&materials))
+                                .with_children(|parent| {
+                                    parent
+                                        .spawn_bundle(row_button(&materials))
+                                        .with_children(|parent| {
+                                            parent.spawn_bundle(compact_text(
+                                                &asset_server,
+                                                &materials,
+                                                "Reset to Defaults",
+                                            ));
+                                        })
+                                        .insert(MenuButton::ResetBindings);
+                                    parent
+                                        .spawn_bundle(row_button(&materials))
+                                        .with_children(|parent| {
+                                            parent.spawn_bundle(compact_text(
+                                                &asset_server,
+                                                &materials,
+                                                "Back",
+                                            ));
+                                        })
+                                        .insert(MenuButton::Options);
+                                });
+                        });
+                });
+        });
+}
+
+pub(super) fn stop_rebinding(mut rebinding: ResMut<Rebinding>) {
+    rebinding.0 = None;
+}
+
+/// Binds the next key or gamepad input pressed to the action being rebound, swapping bindings
+/// with any action that already used it.
+pub(super) fn capture_binding(
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<InputBindings>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    mut menu_actions: ResMut<Input<MenuAction>>,
+    mut messages: Query<&mut Text, With<ControlsMessage>>,
+) {
+    // The input that started rebinding, e.g. a confirm button, must not be bound right away.
+    if rebinding.is_changed() {
+        return;
+    }
+    let (action, device) = match rebinding.0 {
+        Some(target) => target,
+        None => return,
+    };
+    // Escape cancels, keeping the binding as it was.
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        rebinding.0 = None;
+        // Escape must not also leave the controls screen.
+        menu_actions.clear();
+        for mut text in messages.iter_mut() {
+            text.sections[0].value = CONTROLS_HINT.to_string();
+        }
+        return;
+    }
+    let conflict = match device {
+        BindingDevice::Keyboard => match keyboard_input.get_just_pressed().next() {
+            Some(key) => bindings.bind_key(action, *key),
+            None => return,
+        },
+        BindingDevice::Gamepad => {
+            match GamepadInput::detect(&gamepads, &gamepad_buttons, &gamepad_axes) {
+                Some(input) => bindings.bind_gamepad(action, input),
+                None => return,
+            }
+        }
+    };
+    rebinding.0 = None;
+    bindings.save();
+    // The bound input must not also navigate the menu.
+    menu_actions.clear();
+
+    let message = match conflict {
+        Some(other) => format!(
+            "{} already used it, it now uses the previous {} binding",
+            other.label(),
+            action.label()
+        ),
+        None => CONTROLS_HINT.to_string(),
+    };
+    for mut text in messages.iter_mut() {
+        text.sections[0].value = message.clone();
+    }
+}
+
+pub(super) fn update_binding_labels(
+    bindings: Res<InputBindings>,
+    rebinding: Res<Rebinding>,
+    buttons: Query<(&MenuButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !bindings.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+    for (button, children) in buttons.iter() {
+        if let MenuButton::Rebind(action, device) = button {
+            for child in children.iter() {
+                if let Ok(mut text) = texts.get_mut(*child) {
+                    text.sections[0].value = binding_label(&bindings, &rebinding, *action, *device);
+                }
+            }
+        }
+    }
+}
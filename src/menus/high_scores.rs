@@ -0,0 +1,192 @@
+use super::super::game::{HighScore, HighScores, LevelProgress, LevelSeed, LevelTime, Score};
+use super::{
+    border, button_text, compact_text, info_text, menu_background, root, row, row_button,
+    MenuButton, MenuMaterials,
+};
+use crate::actions::MenuAction;
+use bevy::prelude::*;
+
+const MAX_NAME_LENGTH: usize = 12;
+const DEFAULT_NAME: &str = "Player";
+
+/// The name being typed for a new high score, if any.
+#[derive(Default)]
+pub struct NameEntry(pub Option<String>);
+
+/// Index of the seed shown on the high scores screen.
+#[derive(Default)]
+pub struct HighScoresPage(pub usize);
+
+#[derive(Component)]
+pub(super) struct HighScorePrompt;
+
+#[derive(Component)]
+pub(super) struct HighScoreTable;
+
+fn prompt_text(name: &str) -> String {
+    format!("New high score! Name: {}_", name)
+}
+
+/// Adds the name prompt to the Game Over screen when the run made it into the high scores.
+pub(super) fn spawn_name_entry(
+    parent: &mut ChildBuilder,
+    asset_server: &Res<AssetServer>,
+    materials: &Res<MenuMaterials>,
+    name_entry: &mut NameEntry,
+) {
+    let name = String::new();
+    parent
+        .spawn_bundle(info_text(asset_server, materials, &prompt_text(&name)))
+        .insert(HighScorePrompt);
+    name_entry.0 = Some(name);
+}
+
+/// Types the name with the keyboard. Enter, or the gamepad's confirm button, saves the entry.
+pub(super) fn enter_name(
+    mut name_entry: ResMut<NameEntry>,
+    mut high_scores: ResMut<HighScores>,
+    mut menu_actions: ResMut<Input<MenuAction>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    seed: Res<LevelSeed>,
+    progress: Res<LevelProgress>,
+    score: Res<Score>,
+    level_time: Res<LevelTime>,
+    mut prompts: Query<&mut Text, With<HighScorePrompt>>,
+) {
+    let mut name = match &name_entry.0 {
+        Some(name) => name.clone(),
+        None => return,
+    };
+    for character in characters.iter() {
+        if !character.char.is_control() && name.chars().count() < MAX_NAME_LENGTH {
+            name.push(character.char);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        name.pop();
+    }
+    let submitted = keyboard_input.just_pressed(KeyCode::Return)
+        || gamepad_buttons
+            .get_just_pressed()
+            .any(|button| button.1 == GamepadButtonType::South);
+
+    let prompt = if submitted {
+        let name = match name.trim() {
+            "" => DEFAULT_NAME.to_string(),
+            name => name.to_string(),
+        };
+        let prompt = format!("Saved as {}", name);
+        high_scores.insert(HighScore {
+            name,
+            seed: seed.value,
+            score: progress.score + score.total(),
+            level: progress.level,
+            time: progress.time + level_time.elapsed,
+        });
+        high_scores.save();
+        name_entry.0 = None;
+        // The key saving the name must not also press a button.
+        menu_actions.clear();
+        prompt
+    } else if name_entry.0.as_ref() != Some(&name) {
+        let prompt = prompt_text(&name);
+        name_entry.0 = Some(name);
+        prompt
+    } else {
+        return;
+    };
+    for mut text in prompts.iter_mut() {
+        text.sections[0].value = prompt.clone();
+    }
+}
+
+pub(super) fn stop_name_entry(mut name_entry: ResMut<NameEntry>) {
+    name_entry.0 = None;
+}
+
+pub(super) fn setup_high_scores_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    materials: Res<MenuMaterials>,
+    mut page: ResMut<HighScoresPage>,
+) {
+    page.0 = 0;
+    commands.spawn_bundle(UiCameraBundle::default());
+
+    let mut high_scores_border = border(&materials);
+    high_scores_border.style.size = Size::new(Val::Px(600.0), Val::Auto);
+
+    commands
+        .spawn_bundle(root(&materials))
+        .with_children(|parent| {
+            parent.spawn_bundle(button_text(&asset_server, &materials, "High Scores"));
+            parent
+                .spawn_bundle(high_scores_border)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(menu_background(&materials))
+                        .with_children(|parent| {
+                            parent
+                                .spawn_bundle(info_text(&asset_server, &materials, ""))
+                                .insert(HighScoreTable);
+                            parent
+                                .spawn_bundle(row(&materials))
+                                .with_children(|parent| {
+                                    for (label, button) in [
+                                        ("Previous Seed", MenuButton::PreviousSeed),
+                                        ("Next Seed", MenuButton::NextSeed),
+                                        ("Back", MenuButton::BackToMainMenu),
+                                    ] {
+                                        parent
+                                            .spawn_bundle(row_button(&materials))
+                                            .with_children(|parent| {
+                                                parent.spawn_bundle(compact_text(
+                                                    &asset_server,
+                                                    &materials,
+                                                    label,
+                                                ));
+                                            })
+                                            .insert(button);
+                                    }
+                                });
+                        });
+                });
+        });
+}
+
+pub(super) fn update_high_score_table(
+    high_scores: Res<HighScores>,
+    mut page: ResMut<HighScoresPage>,
+    mut tables: Query<&mut Text, With<HighScoreTable>>,
+) {
+    let seeds = high_scores.seeds();
+    let table = if seeds.is_empty() {
+        "No high scores yet".to_string()
+    } else {
+        if page.0 >= seeds.len() {
+            page.0 = seeds.len() - 1;
+        }
+        let seed = seeds[page.0];
+        let mut lines = vec![format!("Seed {} ({}/{})", seed, page.0 + 1, seeds.len())];
+        for (rank, entry) in high_scores.top(seed).iter().enumerate() {
+            let seconds = entry.time as u32;
+            lines.push(format!(
+                "{}. {} - {} - Level {} - {}:{:02}",
+                rank + 1,
+                entry.name,
+                entry.score,
+                entry.level + 1,
+                seconds / 60,
+                seconds % 60
+            ));
+        }
+        lines.join("\n")
+    };
+    for mut text in tables.iter_mut() {
+        if text.sections[0].value != table {
+            text.sections[0].value = table.clone();
+        }
+    }
+}
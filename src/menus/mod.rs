@@ -1,62 +1,198 @@
+use super::actions::{GameAction, InputBindings, MenuAction};
+use super::game::{
+    ContinueRunEvent, HighScores, LevelProgress, LevelSeed, LevelTime, SaveRunEvent, SavedRun,
+    Score,
+};
 use super::AppState;
 use bevy::{app::AppExit, prelude::*};
 
 mod common;
 pub use common::*;
 
+mod controls;
+use controls::*;
+
+mod pause;
+use pause::*;
+
+mod navigation;
+use navigation::*;
+
+mod high_scores;
+use high_scores::*;
+
 pub struct MenusPlugin;
 
 #[derive(Component)]
 enum MenuButton {
+    Continue,
     Play,
+    NextLevel,
     BackToMainMenu,
+    Resume,
+    SaveRun,
+    RestartLevel,
+    Options,
+    Controls,
+    Rebind(GameAction, BindingDevice),
+    HighScores,
+    PreviousSeed,
+    NextSeed,
+    ResetBindings,
     Quit,
 }
 
 fn button_press_system(
     buttons: Query<(&Interaction, &MenuButton), (Changed<Interaction>, With<Button>)>,
+    focused: Query<&MenuButton, (With<Focused>, With<Button>)>,
+    menu_actions: Res<Input<MenuAction>>,
     mut state: ResMut<State<AppState>>,
+    mut progress: ResMut<LevelProgress>,
+    score: Res<Score>,
+    level_time: Res<LevelTime>,
+    mut bindings: ResMut<InputBindings>,
+    mut rebinding: ResMut<Rebinding>,
+    name_entry: Res<NameEntry>,
+    mut page: ResMut<HighScoresPage>,
+    mut send_continue_run: EventWriter<ContinueRunEvent>,
+    mut send_save_run: EventWriter<SaveRunEvent>,
     mut exit: EventWriter<AppExit>,
 ) {
-    for (interaction, button) in buttons.iter() {
-        if *interaction == Interaction::Clicked {
-            match button {
-                MenuButton::Play => state
+    let clicked = buttons
+        .iter()
+        .filter(|(interaction, _)| **interaction == Interaction::Clicked)
+        .map(|(_, button)| button);
+    let confirm = !is_input_captured(&rebinding, &name_entry)
+        && menu_actions.just_pressed(MenuAction::Confirm);
+    let confirmed = focused.iter().filter(|_| confirm);
+    for button in clicked.chain(confirmed) {
+        match button {
+            MenuButton::Continue => send_continue_run.send(ContinueRunEvent),
+            MenuButton::Play => {
+                *progress = LevelProgress::default();
+                state
                     .set(AppState::InGame)
-                    .expect("Couldn't switch state to InGame"),
-                MenuButton::BackToMainMenu => state
-                    .set(AppState::MainMenu)
-                    .expect("Couldn't switch state to MainMenu"),
-                MenuButton::Quit => exit.send(AppExit),
-            };
-        }
+                    .expect("Couldn't switch state to InGame")
+            }
+            MenuButton::NextLevel => {
+                progress.score += score.total();
+                progress.time += level_time.elapsed;
+                progress.level += 1;
+                state
+                    .set(AppState::InGame)
+                    .expect("Couldn't switch state to InGame")
+            }
+            MenuButton::BackToMainMenu => state
+                .replace(AppState::MainMenu)
+                .expect("Couldn't switch state to MainMenu"),
+            MenuButton::Resume => state.pop().expect("Couldn't resume the game"),
+            MenuButton::SaveRun => send_save_run.send(SaveRunEvent),
+            MenuButton::RestartLevel => state
+                .replace(AppState::InGame)
+                .expect("Couldn't restart the level"),
+            MenuButton::Options => state
+                .set(AppState::Options)
+                .expect("Couldn't switch state to Options"),
+            MenuButton::Controls => state
+                .set(AppState::Controls)
+                .expect("Couldn't switch state to Controls"),
+            MenuButton::Rebind(action, device) => rebinding.0 = Some((*action, *device)),
+            MenuButton::HighScores => state
+                .set(AppState::HighScores)
+                .expect("Couldn't switch state to HighScores"),
+            MenuButton::PreviousSeed => page.0 = page.0.saturating_sub(1),
+            MenuButton::NextSeed => page.0 += 1,
+            MenuButton::ResetBindings => {
+                *bindings = InputBindings::default();
+                bindings.save();
+            }
+            MenuButton::Quit => exit.send(AppExit),
+        };
     }
 }
 
 impl Plugin for MenusPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<MenuMaterials>()
+            .init_resource::<Rebinding>()
+            .init_resource::<NameEntry>()
+            .init_resource::<HighScoresPage>()
             .add_system(button_system.system())
             .add_system(button_press_system.system())
+            .add_system(focus_navigation.system())
+            .add_system(back_system.system())
             .add_system_set(
                 SystemSet::on_enter(AppState::MainMenu)
                     .with_system(cleanup.system())
                     .with_system(setup_main_menu.system()),
             )
             .add_system_set(SystemSet::on_exit(AppState::MainMenu).with_system(cleanup.system()))
+            .add_system_set(SystemSet::on_exit(AppState::InGame).with_system(cleanup.system()))
+            .add_system_set(
+                SystemSet::on_enter(AppState::Paused).with_system(setup_pause_menu.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Paused).with_system(cleanup_pause_menu.system()),
+            )
             .add_system_set(
                 SystemSet::on_enter(AppState::GameOver)
                     .with_system(cleanup.system())
                     .with_system(setup_game_over_menu.system()),
             )
-            .add_system_set(SystemSet::on_exit(AppState::GameOver).with_system(cleanup.system()))
+            .add_system_set(
+                SystemSet::on_update(AppState::GameOver)
+                    .with_system(update_score_breakdown.system())
+                    .with_system(enter_name.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::GameOver)
+                    .with_system(cleanup.system())
+                    .with_system(stop_name_entry.system()),
+            )
             .add_system_set(
                 SystemSet::on_enter(AppState::BetweenLevels)
                     .with_system(cleanup.system())
                     .with_system(setup_level_success_menu.system()),
             )
+            .add_system_set(
+                SystemSet::on_update(AppState::BetweenLevels)
+                    .with_system(update_score_breakdown.system()),
+            )
             .add_system_set(
                 SystemSet::on_exit(AppState::BetweenLevels).with_system(cleanup.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::Options)
+                    .with_system(cleanup.system())
+                    .with_system(setup_options_menu.system()),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Options).with_system(cleanup.system()))
+            .add_system_set(
+                SystemSet::on_enter(AppState::Controls)
+                    .with_system(cleanup.system())
+                    .with_system(setup_controls_menu.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Controls)
+                    .with_system(capture_binding.system())
+                    .with_system(update_binding_labels.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::HighScores)
+                    .with_system(cleanup.system())
+                    .with_system(setup_high_scores_menu.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::HighScores)
+                    .with_system(update_high_score_table.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::HighScores).with_system(cleanup.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Controls)
+                    .with_system(cleanup.system())
+                    .with_system(stop_rebinding.system()),
             );
     }
 }
@@ -77,6 +213,18 @@ fn setup_main_menu(
                     parent
                         .spawn_bundle(menu_background(&materials))
                         .with_children(|parent| {
+                            if SavedRun::exists() {
+                                parent
+                                    .spawn_bundle(button(&materials))
+                                    .with_children(|parent| {
+                                        parent.spawn_bundle(button_text(
+                                            &asset_server,
+                                            &materials,
+                                            "Continue",
+                                        ));
+                                    })
+                                    .insert(MenuButton::Continue);
+                            }
                             parent
                                 .spawn_bundle(button(&materials))
                                 .with_children(|parent| {
@@ -87,6 +235,26 @@ fn setup_main_menu(
                                     ));
                                 })
                                 .insert(MenuButton::Play);
+                            parent
+                                .spawn_bundle(button(&materials))
+                                .with_children(|parent| {
+                                    parent.spawn_bundle(button_text(
+                                        &asset_server,
+                                        &materials,
+                                        "Options",
+                                    ));
+                                })
+                                .insert(MenuButton::Options);
+                            parent
+                                .spawn_bundle(button(&materials))
+                                .with_children(|parent| {
+                                    parent.spawn_bundle(button_text(
+                                        &asset_server,
+                                        &materials,
+                                        "High Scores",
+                                    ));
+                                })
+                                .insert(MenuButton::HighScores);
                             if !cfg!(target_arch = "wasm32") {
                                 parent
                                     .spawn_bundle(button(&materials))
@@ -108,6 +276,11 @@ fn setup_game_over_menu(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     materials: Res<MenuMaterials>,
+    seed: Res<LevelSeed>,
+    progress: Res<LevelProgress>,
+    score: Res<Score>,
+    high_scores: Res<HighScores>,
+    mut name_entry: ResMut<NameEntry>,
 ) {
     commands.spawn_bundle(UiCameraBundle::default());
 
@@ -115,6 +288,17 @@ fn setup_game_over_menu(
         .spawn_bundle(root(&materials))
         .with_children(|parent| {
             parent.spawn_bundle(button_text(&asset_server, &materials, "Game Over"));
+            parent.spawn_bundle(info_text(
+                &asset_server,
+                &materials,
+                &format!("Level {} - Seed: {}", progress.level + 1, seed.value),
+            ));
+            parent
+                .spawn_bundle(info_text(&asset_server, &materials, ""))
+                .insert(ScoreBreakdown);
+            if high_scores.qualifies(seed.value, progress.score + score.total()) {
+                spawn_name_entry(parent, &asset_server, &materials, &mut name_entry);
+            }
             parent
                 .spawn_bundle(border(&materials))
                 .with_children(|parent| {
@@ -150,6 +334,8 @@ fn setup_level_success_menu(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     materials: Res<MenuMaterials>,
+    seed: Res<LevelSeed>,
+    progress: Res<LevelProgress>,
 ) {
     commands.spawn_bundle(UiCameraBundle::default());
 
@@ -157,6 +343,14 @@ fn setup_level_success_menu(
         .spawn_bundle(root(&materials))
         .with_children(|parent| {
             parent.spawn_bundle(button_text(&asset_server, &materials, "Level Success"));
+            parent.spawn_bundle(info_text(
+                &asset_server,
+                &materials,
+                &format!("Level {} - Seed: {}", progress.level + 1, seed.value),
+            ));
+            parent
+                .spawn_bundle(info_text(&asset_server, &materials, ""))
+                .insert(ScoreBreakdown);
             parent
                 .spawn_bundle(border(&materials))
                 .with_children(|parent| {
@@ -172,7 +366,7 @@ fn setup_level_success_menu(
                                         "Next Level",
                                     ));
                                 })
-                                .insert(MenuButton::Play);
+                                .insert(MenuButton::NextLevel);
                             parent
                                 .spawn_bundle(button(&materials))
                                 .with_children(|parent| {
@@ -188,6 +382,31 @@ fn setup_level_success_menu(
         });
 }
 
+#[derive(Component)]
+struct ScoreBreakdown;
+
+/// Kept up to date rather than written once, as the last points of a level are counted on the
+/// frame after leaving it.
+fn update_score_breakdown(
+    progress: Res<LevelProgress>,
+    score: Res<Score>,
+    mut texts: Query<&mut Text, With<ScoreBreakdown>>,
+) {
+    let breakdown = format!(
+        "Enemies: {}\nLevel: {}\nTime bonus: {}\nNo damage bonus: {}\nTotal: {}",
+        score.kills,
+        score.completion,
+        score.time_bonus,
+        score.no_damage_bonus,
+        progress.score + score.total()
+    );
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != breakdown {
+            text.sections[0].value = breakdown.clone();
+        }
+    }
+}
+
 fn cleanup(mut commands: Commands, query: Query<Entity>) {
     for entity in query.iter() {
         commands.entity(entity).despawn_recursive();
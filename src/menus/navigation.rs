@@ -0,0 +1,95 @@
+use super::super::AppState;
+use super::{Focused, MenuButton, NameEntry, Rebinding};
+use crate::actions::{GameAction, MenuAction};
+use bevy::prelude::*;
+
+/// Whether a screen reads raw input, to rebind it or type a name, which must not also drive the
+/// menu.
+pub(super) fn is_input_captured(rebinding: &Rebinding, name_entry: &NameEntry) -> bool {
+    rebinding.0.is_some() || name_entry.0.is_some()
+}
+
+/// Moves the focus between menu buttons in reading order, and onto buttons hovered by the mouse.
+pub(super) fn focus_navigation(
+    mut commands: Commands,
+    menu_actions: Res<Input<MenuAction>>,
+    rebinding: Res<Rebinding>,
+    name_entry: Res<NameEntry>,
+    buttons: Query<(Entity, &GlobalTransform, Option<&Focused>), With<MenuButton>>,
+    hovered: Query<(Entity, &Interaction), (Changed<Interaction>, With<MenuButton>)>,
+) {
+    let mut ordered: Vec<_> = buttons.iter().collect();
+    if ordered.is_empty() {
+        return;
+    }
+    // UI coordinates go up, so higher buttons come first.
+    ordered.sort_by(|(_, a, _), (_, b, _)| {
+        let (a, b) = (a.translation, b.translation);
+        b.y.partial_cmp(&a.y)
+            .unwrap()
+            .then(a.x.partial_cmp(&b.x).unwrap())
+    });
+    let count = ordered.len();
+    let focused = ordered.iter().position(|(_, _, focused)| focused.is_some());
+
+    let hovered = hovered
+        .iter()
+        .find(|(_, interaction)| **interaction == Interaction::Hovered)
+        .and_then(|(entity, _)| ordered.iter().position(|(other, _, _)| *other == entity));
+    let next = if hovered.is_some() {
+        hovered
+    } else if is_input_captured(&rebinding, &name_entry) {
+        None
+    } else if menu_actions.just_pressed(MenuAction::Down) {
+        Some(focused.map_or(0, |index| (index + 1) % count))
+    } else if menu_actions.just_pressed(MenuAction::Up) {
+        Some(focused.map_or(count - 1, |index| (index + count - 1) % count))
+    } else {
+        None
+    };
+
+    if let Some(next) = next {
+        if Some(next) != focused {
+            if let Some(focused) = focused {
+                commands.entity(ordered[focused].0).remove::<Focused>();
+            }
+            commands.entity(ordered[next].0).insert(Focused);
+        }
+    }
+}
+
+/// Goes back to the previous screen, the pause key also resuming the game.
+pub(super) fn back_system(
+    menu_actions: Res<Input<MenuAction>>,
+    mut actions: ResMut<Input<GameAction>>,
+    rebinding: Res<Rebinding>,
+    name_entry: Res<NameEntry>,
+    mut state: ResMut<State<AppState>>,
+) {
+    if is_input_captured(&rebinding, &name_entry) {
+        return;
+    }
+    let back = menu_actions.just_pressed(MenuAction::Back);
+    let current = state.current().clone();
+    match current {
+        AppState::Paused => {
+            if back || actions.clear_just_pressed(GameAction::Pause) {
+                state.pop().expect("Couldn't resume the game");
+            }
+        }
+        AppState::Options
+        | AppState::GameOver
+        | AppState::BetweenLevels
+        | AppState::HighScores
+            if back =>
+        {
+            state
+                .set(AppState::MainMenu)
+                .expect("Couldn't switch state to MainMenu")
+        }
+        AppState::Controls if back => state
+            .set(AppState::Options)
+            .expect("Couldn't switch state to Options"),
+        _ => (),
+    }
+}
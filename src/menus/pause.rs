@@ -0,0 +1,74 @@
+use super::{border, button, button_text, menu_background, root, MenuButton, MenuMaterials};
+use bevy::prelude::*;
+
+/// Marks the pause overlay, drawn on top of the level which stays spawned underneath.
+#[derive(Component)]
+pub(super) struct PauseMenu;
+
+pub(super) fn setup_pause_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    materials: Res<MenuMaterials>,
+) {
+    // Drawn by the HUD's UI camera.
+    commands
+        .spawn_bundle(root(&materials))
+        .with_children(|parent| {
+            parent.spawn_bundle(button_text(&asset_server, &materials, "Paused"));
+            parent
+                .spawn_bundle(border(&materials))
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(menu_background(&materials))
+                        .with_children(|parent| {
+                            parent
+                                .spawn_bundle(button(&materials))
+                                .with_children(|parent| {
+                                    parent.spawn_bundle(button_text(
+                                        &asset_server,
+                                        &materials,
+                                        "Resume",
+                                    ));
+                                })
+                                .insert(MenuButton::Resume);
+                            parent
+                                .spawn_bundle(button(&materials))
+                                .with_children(|parent| {
+                                    parent.spawn_bundle(button_text(
+                                        &asset_server,
+                                        &materials,
+                                        "Save Game",
+                                    ));
+                                })
+                                .insert(MenuButton::SaveRun);
+                            parent
+                                .spawn_bundle(button(&materials))
+                                .with_children(|parent| {
+                                    parent.spawn_bundle(button_text(
+                                        &asset_server,
+                                        &materials,
+                                        "Restart Level",
+                                    ));
+                                })
+                                .insert(MenuButton::RestartLevel);
+                            parent
+                                .spawn_bundle(button(&materials))
+                                .with_children(|parent| {
+                                    parent.spawn_bundle(button_text(
+                                        &asset_server,
+                                        &materials,
+                                        "Quit to Main Menu",
+                                    ));
+                                })
+                                .insert(MenuButton::BackToMainMenu);
+                        });
+                });
+        })
+        .insert(PauseMenu);
+}
+
+pub(super) fn cleanup_pause_menu(mut commands: Commands, query: Query<Entity, With<PauseMenu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
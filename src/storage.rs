@@ -0,0 +1,81 @@
+//! Small key-value persistence: files in the `save` directory on native, local storage on the web.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use std::{fs, path::PathBuf};
+
+    const SAVE_DIRECTORY: &str = "save";
+
+    fn path(key: &str) -> PathBuf {
+        PathBuf::from(SAVE_DIRECTORY).join(format!("{}.ron", key))
+    }
+
+    pub fn load(key: &str) -> Option<String> {
+        fs::read_to_string(path(key)).ok()
+    }
+
+    pub fn save(key: &str, contents: &str) -> Result<(), String> {
+        fs::create_dir_all(SAVE_DIRECTORY).map_err(|error| error.to_string())?;
+        fs::write(path(key), contents).map_err(|error| error.to_string())
+    }
+
+    pub fn remove(key: &str) {
+        let _ = fs::remove_file(path(key));
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    fn storage_key(key: &str) -> String {
+        format!("platformer.{}", key)
+    }
+
+    pub fn load(key: &str) -> Option<String> {
+        local_storage()?.get_item(&storage_key(key)).ok()?
+    }
+
+    pub fn save(key: &str, contents: &str) -> Result<(), String> {
+        local_storage()
+            .ok_or_else(|| "local storage is unavailable".to_string())?
+            .set_item(&storage_key(key), contents)
+            .map_err(|error| format!("{:?}", error))
+    }
+
+    pub fn remove(key: &str) {
+        if let Some(storage) = local_storage() {
+            let _ = storage.remove_item(&storage_key(key));
+        }
+    }
+}
+
+pub use platform::*;
+
+use bevy::prelude::warn;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Loads and parses the value stored under `key`, ignoring it with a warning if it is invalid.
+/// `description` names the value in the warning.
+pub fn load_ron<T: DeserializeOwned>(key: &str, description: &str) -> Option<T> {
+    let contents = load(key)?;
+    match ron::de::from_str(&contents) {
+        Ok(value) => Some(value),
+        Err(error) => {
+            warn!("Ignoring invalid {}: {}", description, error);
+            None
+        }
+    }
+}
+
+/// Stores `value` under `key`, warning if it couldn't be saved.
+pub fn save_ron<T: Serialize>(key: &str, description: &str, value: &T) {
+    let result = ron::ser::to_string_pretty(value, Default::default())
+        .map_err(|error| error.to_string())
+        .and_then(|contents| save(key, &contents));
+    if let Err(error) = result {
+        warn!("Couldn't save {}: {}", description, error);
+    }
+}